@@ -0,0 +1,53 @@
+use thirtyfour::WebDriver;
+
+use crate::error::Error;
+use crate::site::SiteAdapter;
+
+const START_SCRIPT: &str = r#"
+    performance.clearResourceTimings();
+    performance.setResourceTimingBufferSize(10000);
+"#;
+
+const STOP_SCRIPT: &str = r#"
+    return performance.getEntriesByType("resource").map(e => e.name);
+"#;
+
+/// Drop network entries collected so far in the current frame, so that only
+/// requests made by the player after this call are collected.
+pub async fn start_listening(wd: &WebDriver) -> Result<(), Error> {
+    wd.execute(START_SCRIPT, Vec::new()).await?;
+    Ok(())
+}
+
+/// Read urls requested by the current frame from the browser's performance
+/// timeline, keeping only stream requests.
+pub async fn stop_listening(
+    wd: &WebDriver,
+    site: &dyn SiteAdapter,
+) -> Result<Vec<String>, Error> {
+    let urls: Vec<String> =
+        wd.execute(STOP_SCRIPT, Vec::new()).await?.convert()?;
+    Ok(urls
+        .into_iter()
+        .map(|url| with_explicit_port(&url))
+        .filter(|url| site.is_stream_url(url))
+        .collect())
+}
+
+/// Browser omits default ports, but proxy reports them, e.g.
+/// `https://host:443/path`, so keep urls in the same shape.
+fn with_explicit_port(url: &str) -> String {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return url.to_string();
+    };
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if host.contains(':') {
+        return url.to_string();
+    }
+    let port = match scheme {
+        "https" => 443,
+        "http" => 80,
+        _ => return url.to_string(),
+    };
+    format!("{scheme}://{host}:{port}{path}")
+}
@@ -0,0 +1,241 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use serde::{Deserialize, Serialize};
+
+use crate::cookies::CookieFormat;
+use crate::error::EXIT_CODES_HELP;
+use crate::failed::{FailureKind, FAILED_VIDEOS_FILE};
+use crate::journal::{Resume, QUEUE_JOURNAL_FILE};
+use crate::logging::LogFormat;
+use crate::run::auth::{LoginCheck, LoginForm};
+use crate::run::browser::Browser;
+use crate::run::capture::CaptureMode;
+use crate::run::{BrowserOptions, CrawlOptions, LoginOptions};
+use crate::site::Site;
+
+/// Robot to download medical videos
+#[derive(Parser, Debug, Serialize, Deserialize)]
+#[command(version, about, long_about = None, after_help = EXIT_CODES_HELP)]
+#[serde(deny_unknown_fields)]
+pub struct Args {
+    #[command(subcommand)]
+    #[serde(skip)]
+    pub command: Option<Command>,
+
+    /// Config file, `video_downloader.toml` in the current directory or
+    /// `video_downloader/config.toml` in the XDG config directory by default
+    #[arg(long)]
+    #[serde(skip)]
+    pub config: Option<PathBuf>,
+
+    /// Account profile to use, from `<profiles-dir>/<name>`. It has its own
+    /// cookie jar, credentials file and `profile.toml` with site settings
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Directory with account profiles
+    #[arg(long, default_value = "profiles")]
+    pub profiles_dir: PathBuf,
+
+    /// Authentication email, `VIDEO_DOWNLOADER_EMAIL` env var, credentials
+    /// file or prompt are used if not set
+    #[arg(short, long)]
+    pub email: Option<String>,
+
+    /// File with `email = ...` and `password = ...` lines, must be readable
+    /// by owner only. Password is taken from `VIDEO_DOWNLOADER_PASSWORD` env
+    /// var first, and prompted for if found nowhere
+    #[arg(long)]
+    pub credentials_file: Option<PathBuf>,
+
+    /// Address of the running webdriver (geckodriver or chromedriver)
+    #[arg(short, long, visible_alias = "webdriver-address", default_value_t = String::from("http://localhost:4444"))]
+    pub geckodriver_address: String,
+
+    /// Spawn the webdriver on a free port instead of connecting to
+    /// `--geckodriver-address`
+    #[arg(long)]
+    pub spawn_driver: bool,
+
+    /// Webdriver binary to spawn, `geckodriver` or `chromedriver` from PATH
+    /// by default
+    #[arg(long, requires = "spawn_driver")]
+    pub driver_path: Option<PathBuf>,
+
+    /// Browser to drive
+    #[arg(long, value_enum, default_value_t = Browser::Firefox)]
+    pub browser: Browser,
+
+    /// Run browser without a visible window
+    #[arg(long)]
+    pub headless: bool,
+
+    #[arg(short, long, default_value_t = 8080)]
+    pub proxy_port: u16,
+
+    /// How to capture player requests
+    #[arg(long, value_enum, default_value_t = CaptureMode::Proxy)]
+    pub capture: CaptureMode,
+
+    /// Number of parallel browser sessions, in proxy mode session `N`
+    /// uses proxy port `--proxy-port + N`
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub sessions: u16,
+
+    /// Platform the course is hosted on
+    #[arg(long, value_enum, default_value_t = Site::Getcourse)]
+    pub site: Site,
+
+    #[arg(short, long, default_value_t = String::from("/teach/control"))]
+    pub base: String,
+    #[arg(short, long, default_value_t = String::from("/teach/control/stream/index"))]
+    pub root: String,
+    #[arg(short, long, default_value_t = String::from("https://universkill.ru"))]
+    pub domain: String,
+    #[arg(short, long, default_value_t = String::from("https://universkill.ru/cms/system/login?required=true"))]
+    pub auth_url: String,
+
+    /// Open login page in the browser window and wait for you to log in,
+    /// e.g. to solve a captcha or enter a confirmation code
+    #[arg(long, conflicts_with = "headless")]
+    pub interactive_login: bool,
+
+    /// Css selector of the login form email input
+    #[arg(long, default_value = "input.form-field-email")]
+    pub login_email_selector: String,
+
+    /// Css selector of the login form password input
+    #[arg(long, default_value = "input.form-field-password")]
+    pub login_password_selector: String,
+
+    /// Css selector of the login form submit button
+    #[arg(long, default_value = "button.btn-success-sech")]
+    pub login_submit_selector: String,
+
+    /// Condition telling that login has succeeded, `url-change`,
+    /// `element:<css>` or `no-element:<css>`, may be repeated
+    #[arg(long, default_value = "url-change")]
+    pub login_success: Vec<LoginCheck>,
+
+    /// File to keep session cookies in, json or netscape cookies.txt
+    #[arg(long, default_value = "cookie.txt")]
+    pub cookie_file: PathBuf,
+
+    /// Format to store cookies in, same as the existing cookie file if not
+    /// set, json for a new one
+    #[arg(long, value_enum)]
+    pub cookie_format: Option<CookieFormat>,
+
+    /// Directory to save videos into
+    #[arg(short, long, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// Continue downloads left unfinished by a crashed run
+    #[arg(long, value_enum, default_value_t = Resume::Ask)]
+    pub resume: Resume,
+
+    /// If you need to download only specified videos
+    #[arg(long)]
+    pub path_to_videos_info_file: Option<PathBuf>,
+
+    /// Write an `.m3u` playlist of lessons into each section directory,
+    /// next to the course `index.html`
+    #[arg(long)]
+    pub playlists: bool,
+
+    /// Format of log lines
+    #[arg(long, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// File to append logs to
+    #[arg(long, default_value = "downloader.log")]
+    pub log_file: PathBuf,
+
+    /// Log to the terminal only
+    #[arg(long)]
+    pub no_log_file: bool,
+
+    /// Log more, `-vv` for trace logs of the downloader. Logs of other
+    /// crates are set with `RUST_LOG`
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log less, `-qq` for errors only
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub quiet: u8,
+}
+
+impl Args {
+    /// Options of the crawler, everything but logging and the subcommand.
+    pub fn crawl_options(&self) -> CrawlOptions {
+        CrawlOptions {
+            site: self.site,
+            domain: self.domain.clone(),
+            base: self.base.clone(),
+            root: self.root.clone(),
+            sessions: self.sessions,
+            output_dir: self.output_dir.clone(),
+            playlists: self.playlists,
+            resume: self.resume,
+            journal_file: self.output_dir.join(QUEUE_JOURNAL_FILE),
+            failed_file: match &self.command {
+                Some(Command::Retry {
+                    file: Some(file), ..
+                }) => file.clone(),
+                _ => self.output_dir.join(FAILED_VIDEOS_FILE),
+            },
+            browser: BrowserOptions {
+                browser: self.browser,
+                headless: self.headless,
+                webdriver_address: self.geckodriver_address.clone(),
+                spawn_driver: self.spawn_driver,
+                driver_path: self.driver_path.clone(),
+                capture: self.capture,
+                proxy_port: self.proxy_port,
+            },
+            login: LoginOptions {
+                auth_url: self.auth_url.clone(),
+                email: self.email.clone(),
+                credentials_file: self.credentials_file.clone(),
+                interactive: self.interactive_login,
+                form: LoginForm {
+                    email: self.login_email_selector.clone(),
+                    password: self.login_password_selector.clone(),
+                    submit: self.login_submit_selector.clone(),
+                    success: self.login_success.clone(),
+                },
+                cookie_file: self.cookie_file.clone(),
+                cookie_format: self.cookie_format,
+            },
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Download videos which failed in previous runs again
+    Retry {
+        /// Retry only videos failed for this reason, may be repeated,
+        /// all failed videos by default
+        #[arg(long, value_enum)]
+        kind: Vec<FailureKind>,
+
+        /// File with failed videos, `failed_videos_data.json` in the output
+        /// directory by default
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Inspect configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Print effective configuration, merged from config file, profile
+    /// and command line
+    Show,
+}
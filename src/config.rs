@@ -0,0 +1,120 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory};
+
+use crate::error::Error;
+use crate::Args;
+
+/// Config file looked up in the current directory.
+pub const LOCAL_CONFIG_FILE: &str = "video_downloader.toml";
+
+/// Find config file: `video_downloader.toml` in the current directory,
+/// or `video_downloader/config.toml` in the XDG config directory.
+pub fn find_config_file() -> Option<PathBuf> {
+    let local = PathBuf::from(LOCAL_CONFIG_FILE);
+    if local.is_file() {
+        return Some(local);
+    }
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    let global = config_home.join("video_downloader").join("config.toml");
+    global.is_file().then_some(global)
+}
+
+/// Override `args` with the options from the config file. Keys are the
+/// same as the long option names with `_` instead of `-`, options passed
+/// on the command line always win.
+pub fn apply(
+    args: Args,
+    matches: &ArgMatches,
+    path: &Path,
+) -> Result<Args, Error> {
+    let contents = read_to_string(path).map_err(|e| {
+        Error::Config(format!(
+            "Failed to read config file {}: {e}",
+            path.display()
+        ))
+    })?;
+    let file: toml::Table = toml::from_str(&contents).map_err(|e| {
+        Error::Config(format!(
+            "Failed to parse config file {}: {e}",
+            path.display()
+        ))
+    })?;
+
+    let command = Args::command();
+    let mut merged = toml::Table::try_from(&args)
+        .map_err(|e| Error::Config(format!("Failed to merge config: {e}")))?;
+    for (key, value) in file {
+        if !command
+            .get_arguments()
+            .any(|arg| arg.get_id() == key.as_str())
+        {
+            return Err(Error::Config(format!(
+                "Unknown option `{key}` in config file {}",
+                path.display()
+            )));
+        }
+        if matches.value_source(&key) != Some(ValueSource::CommandLine) {
+            merged.insert(key, value);
+        }
+    }
+
+    let mut merged: Args = merged.try_into().map_err(|e| {
+        Error::Config(format!(
+            "Bad value in config file {}: {e}",
+            path.display()
+        ))
+    })?;
+    // Clap only checks conflicts between options of the command line
+    if merged.headless && merged.interactive_login {
+        return Err(Error::Config(format!(
+            "`headless` and `interactive_login` can't be used together, set \
+            in config file {} or on the command line",
+            path.display()
+        )));
+    }
+    merged.command = args.command;
+    merged.config = args.config;
+    Ok(merged)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use clap::{CommandFactory, FromArgMatches};
+
+    use crate::Args;
+
+    #[test]
+    fn command_line_overrides_config_file() {
+        Args::command().debug_assert();
+
+        let path = std::env::temp_dir()
+            .join(format!("video_downloader_{}.toml", std::process::id()));
+        let mut file = std::fs::File::create(&path).unwrap();
+        writeln!(file, "domain = \"https://example.com\"").unwrap();
+        writeln!(file, "proxy_port = 9090").unwrap();
+        writeln!(file, "login_success = [\"element:#profile\"]").unwrap();
+
+        let matches = Args::command()
+            .try_get_matches_from(["video_downloader", "--proxy-port", "7070"])
+            .unwrap();
+        let args = Args::from_arg_matches(&matches).unwrap();
+        let args = super::apply(args, &matches, &path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(args.domain, "https://example.com");
+        assert_eq!(args.proxy_port, 7070);
+        assert_eq!(args.login_success[0].to_string(), "element:#profile");
+        assert_eq!(args.base, "/teach/control");
+    }
+}
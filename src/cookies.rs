@@ -0,0 +1,187 @@
+use std::fs::read_to_string;
+use std::path::Path;
+
+use thirtyfour::Cookie;
+
+use crate::error::Error;
+
+const NETSCAPE_HEADER: &str = "# Netscape HTTP Cookie File";
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// Format of the cookie file.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum CookieFormat {
+    /// Webdriver cookies serialized as json
+    Json,
+    /// Netscape cookies.txt, as used by curl, yt-dlp and browser extensions
+    Netscape,
+}
+
+impl CookieFormat {
+    /// Guess format of the file contents.
+    pub fn detect(contents: &str) -> Self {
+        if contents.trim_start().starts_with(['[', '{']) {
+            CookieFormat::Json
+        } else {
+            CookieFormat::Netscape
+        }
+    }
+}
+
+/// Read cookies from the file, in either json or netscape format.
+pub fn read_cookies(path: &Path) -> Result<Vec<Cookie>, Error> {
+    let contents = read_to_string(path)?;
+    match CookieFormat::detect(&contents) {
+        CookieFormat::Json => serde_json::from_str(&contents)
+            .map_err(|e| Error::Auth(format!("Bad json cookies: {e}"))),
+        CookieFormat::Netscape => parse_netscape(&contents),
+    }
+}
+
+/// Serialize cookies into the file contents of the given `format`.
+pub fn format_cookies(
+    cookies: &[Cookie],
+    format: CookieFormat,
+) -> Result<String, Error> {
+    match format {
+        CookieFormat::Json => serde_json::to_string(cookies)
+            .map_err(|e| Error::Auth(format!("Bad cookies: {e}"))),
+        CookieFormat::Netscape => Ok(to_netscape(cookies)),
+    }
+}
+
+/// Parse netscape cookies.txt. Expiry `0` means session cookie.
+pub fn parse_netscape(contents: &str) -> Result<Vec<Cookie>, Error> {
+    let mut cookies = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        // Curl marks http-only cookies with a prefix looking like a comment
+        let line = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [domain, _subdomains, path, secure, expiry, name, value] =
+            fields[..]
+        else {
+            return Err(Error::Auth(format!(
+                "Line {}: expected 7 tab separated fields, got {}",
+                idx + 1,
+                fields.len()
+            )));
+        };
+        let expiry: i64 = expiry.parse().map_err(|e| {
+            Error::Auth(format!("Line {}: bad expiry: {e}", idx + 1))
+        })?;
+
+        let mut cookie = Cookie::new(name, value);
+        cookie.set_domain(domain);
+        cookie.set_path(path);
+        cookie.set_secure(secure.eq_ignore_ascii_case("TRUE"));
+        if expiry != 0 {
+            cookie.set_expiry(expiry);
+        }
+        cookies.push(cookie);
+    }
+    Ok(cookies)
+}
+
+/// Keep cookies which the browser accepts on the pages of `domain`, e.g.
+/// `https://example.com`. Cookies exported from a browser are usually for
+/// many sites.
+pub fn for_domain(cookies: Vec<Cookie>, domain: &str) -> Vec<Cookie> {
+    let host = domain
+        .split_once("://")
+        .map_or(domain, |(_, rest)| rest)
+        .split(['/', ':'])
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    cookies
+        .into_iter()
+        .filter(|cookie| {
+            let Some(domain) = &cookie.domain else {
+                return true;
+            };
+            let domain = domain.trim_start_matches('.').to_ascii_lowercase();
+            let matches =
+                host == domain || host.ends_with(&format!(".{domain}"));
+            if !matches {
+                tracing::debug!(
+                    "Skipping cookie {} of another domain {domain}",
+                    cookie.name
+                );
+            }
+            matches
+        })
+        .collect()
+}
+
+/// Write cookies in netscape cookies.txt format.
+pub fn to_netscape(cookies: &[Cookie]) -> String {
+    let mut out = String::from(NETSCAPE_HEADER);
+    out.push('\n');
+    for cookie in cookies {
+        let domain = cookie.domain.as_deref().unwrap_or_default();
+        let bool_str = |b: bool| if b { "TRUE" } else { "FALSE" };
+        out += &format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            domain,
+            bool_str(domain.starts_with('.')),
+            cookie.path.as_deref().unwrap_or("/"),
+            bool_str(cookie.secure.unwrap_or_default()),
+            cookie.expiry.unwrap_or_default(),
+            cookie.name,
+            cookie.value,
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{for_domain, parse_netscape, to_netscape, CookieFormat};
+
+    #[test]
+    fn netscape_roundtrip() {
+        let contents = "# Netscape HTTP Cookie File\n\
+            .universkill.ru\tTRUE\t/\tTRUE\t1893456000\tPHPSESSID5\tabc\n\
+            #HttpOnly_universkill.ru\tFALSE\t/teach\tFALSE\t0\tgc_uid\t42\n";
+        assert_eq!(CookieFormat::detect(contents), CookieFormat::Netscape);
+
+        let cookies = parse_netscape(contents).unwrap();
+        assert_eq!(cookies.len(), 2);
+        assert_eq!(cookies[0].expiry, Some(1893456000));
+        assert_eq!(cookies[1].expiry, None);
+        assert_eq!(cookies[1].path.as_deref(), Some("/teach"));
+
+        let written = to_netscape(&cookies);
+        let reparsed = parse_netscape(&written).unwrap();
+        assert_eq!(reparsed[0].domain.as_deref(), Some(".universkill.ru"));
+        assert_eq!(reparsed[1].value, "42");
+    }
+
+    #[test]
+    fn keeps_cookies_of_course_domain() {
+        let contents = "# Netscape HTTP Cookie File\n\
+            .universkill.ru\tTRUE\t/\tTRUE\t0\tPHPSESSID5\tabc\n\
+            www.universkill.ru\tFALSE\t/\tTRUE\t0\tgc_uid\t42\n\
+            .google.com\tTRUE\t/\tTRUE\t0\tNID\txyz\n\
+            universkill.ru.evil.com\tFALSE\t/\tTRUE\t0\tsid\t1\n";
+        let cookies = parse_netscape(contents).unwrap();
+
+        let kept = for_domain(cookies, "https://universkill.ru:443/teach");
+        let names: Vec<_> = kept.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["PHPSESSID5"]);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fs::read_to_string;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+use zeroize::Zeroizing;
+
+use crate::error::Error;
+
+pub const EMAIL_ENV: &str = "VIDEO_DOWNLOADER_EMAIL";
+pub const PASSWORD_ENV: &str = "VIDEO_DOWNLOADER_PASSWORD";
+
+/// Login credentials, password is wiped from memory on drop.
+pub struct Credentials {
+    pub email: String,
+    pub password: Zeroizing<String>,
+}
+
+/// Where to look for credentials. Every field is taken from the first
+/// source which has it: explicit email, environment variables,
+/// credentials file and finally interactive prompt.
+#[derive(Clone, Debug, Default)]
+pub struct CredentialSource {
+    email: Option<String>,
+    file: Option<PathBuf>,
+}
+
+impl CredentialSource {
+    pub fn new(email: Option<String>, file: Option<PathBuf>) -> Self {
+        CredentialSource { email, file }
+    }
+
+    /// Resolve credentials. Called right before every login, so the
+    /// password is not kept in memory between logins.
+    pub fn resolve(&self) -> Result<Credentials, Error> {
+        let mut email =
+            self.email.clone().or_else(|| std::env::var(EMAIL_ENV).ok());
+        let mut password = std::env::var(PASSWORD_ENV).ok().map(Zeroizing::new);
+
+        if email.is_none() || password.is_none() {
+            if let Some(path) = &self.file {
+                let file = read_credentials_file(path)?;
+                email = email.or(file.email);
+                password = password.or(file.password);
+            }
+        }
+
+        if (email.is_none() || password.is_none())
+            && !std::io::stdin().is_terminal()
+        {
+            return Err(Error::Auth(format!(
+                "No credentials found, set {EMAIL_ENV} and {PASSWORD_ENV}, \
+                use --credentials-file or run in a terminal"
+            )));
+        }
+        let email = match email {
+            Some(email) => email,
+            None => prompt("Email: ")?,
+        };
+        let password = match password {
+            Some(password) => password,
+            None => Zeroizing::new(rpassword::prompt_password("Password: ")?),
+        };
+        Ok(Credentials { email, password })
+    }
+}
+
+struct FileCredentials {
+    email: Option<String>,
+    password: Option<Zeroizing<String>>,
+}
+
+/// Read `email = ...` and `password = ...` lines from the file, which
+/// must not be accessible by anyone but the owner.
+fn read_credentials_file(path: &Path) -> Result<FileCredentials, Error> {
+    check_permissions(path)?;
+    let contents = Zeroizing::new(read_to_string(path).map_err(|e| {
+        Error::Auth(format!(
+            "Failed to read credentials file {}: {e}",
+            path.display()
+        ))
+    })?);
+
+    let mut credentials = FileCredentials {
+        email: None,
+        password: None,
+    };
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+            Some(("email", value)) => {
+                credentials.email = Some(value.to_string())
+            }
+            Some(("password", value)) => {
+                credentials.password = Some(Zeroizing::new(value.to_string()))
+            }
+            _ => tracing::warn!(
+                "Unknown line in credentials file {}",
+                path.display()
+            ),
+        }
+    }
+    Ok(credentials)
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = std::fs::metadata(path)?.permissions().mode();
+    if mode & 0o077 != 0 {
+        return Err(Error::Auth(format!(
+            "Credentials file {} is accessible by other users, \
+            run `chmod 600` on it",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path) -> Result<(), Error> {
+    Ok(())
+}
+
+pub(crate) fn prompt(message: &str) -> Result<String, Error> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(message.as_bytes())?;
+    stdout.flush()?;
+    let mut line = String::new();
+    std::io::stdin().lock().read_line(&mut line)?;
+    Ok(line.trim().to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::read_credentials_file;
+    use crate::error::Error;
+
+    #[test]
+    fn reads_only_private_credentials_file() {
+        let path = std::env::temp_dir().join(format!(
+            "video_downloader_credentials_{}",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            "# account of the course\nemail = me@example.com\n\
+            password = p=ss#word\n",
+        )
+        .unwrap();
+        let chmod = |mode| {
+            std::fs::set_permissions(
+                &path,
+                std::fs::Permissions::from_mode(mode),
+            )
+            .unwrap()
+        };
+
+        chmod(0o644);
+        let shared = read_credentials_file(&path);
+        chmod(0o600);
+        let private = read_credentials_file(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(shared, Err(Error::Auth(_))));
+        let private = private.unwrap();
+        assert_eq!(private.email.as_deref(), Some("me@example.com"));
+        assert_eq!(private.password.as_deref().unwrap(), "p=ss#word");
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::process::ExitStatus;
+
+use thirtyfour::error::WebDriverError;
+
+use crate::shutdown::INTERRUPTED_EXIT_CODE;
+
+/// Exit codes shown in `--help`, keep in sync with [`Error::exit_code`].
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0    success
+  1    internal error
+  2    bad config file, profile, command line or input file
+  3    authentication failed
+  4    webdriver could not be started or failed
+  5    interceptor proxy could not be started
+  6    player urls could not be captured
+  7    some videos failed to download
+  8    io error
+  130  interrupted with Ctrl-C";
+
+/// Errors of the downloader, each kind has its own stable exit code.
+///
+/// | Code | Kind                                                 |
+/// |------|------------------------------------------------------|
+/// | 0    | Success                                              |
+/// | 1    | [`Error::Internal`]                                  |
+/// | 2    | [`Error::Config`], also used by clap for usage errors |
+/// | 3    | [`Error::Auth`]                                      |
+/// | 4    | [`Error::Browser`], [`Error::WebDriver`]             |
+/// | 5    | [`Error::Proxy`]                                     |
+/// | 6    | [`Error::Capture`]                                   |
+/// | 7    | [`Error::Download`]                                  |
+/// | 8    | [`Error::Io`]                                        |
+/// | 130  | [`Error::Interrupted`]                               |
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// Bad config file, profile, command line or input file
+    #[error("Config error: {0}")]
+    Config(String),
+    /// Credentials are missing or login did not succeed
+    #[error("Authentication error: {0}")]
+    Auth(String),
+    /// Webdriver could not be started or reached
+    #[error("Browser error: {0}")]
+    Browser(String),
+    /// Webdriver command failed
+    #[error("Webdriver error: {0}")]
+    WebDriver(#[from] Box<WebDriverError>),
+    /// Interceptor proxy could not be started
+    #[error("Proxy error: {0}")]
+    Proxy(String),
+    /// Player urls could not be collected
+    #[error("Capture error: {0}")]
+    Capture(String),
+    /// Some videos failed to download, they are kept in `file`
+    #[error("{count} videos failed to download, see {}", file.display())]
+    Download { count: usize, file: PathBuf },
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    /// Data could not be serialized or a task has crashed
+    #[error("Internal error: {0}")]
+    Internal(String),
+    /// Stopped with Ctrl-C, pending videos are kept as failed
+    #[error("Interrupted, pending videos are kept for `retry`")]
+    Interrupted,
+}
+
+impl Error {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Config(_) => 2,
+            Error::Auth(_) => 3,
+            Error::Browser(_) | Error::WebDriver(_) => 4,
+            Error::Proxy(_) => 5,
+            Error::Capture(_) => 6,
+            Error::Internal(_) => 1,
+            Error::Download { .. } => 7,
+            Error::Io(_) => 8,
+            Error::Interrupted => INTERRUPTED_EXIT_CODE,
+        }
+    }
+}
+
+impl From<WebDriverError> for Error {
+    fn from(e: WebDriverError) -> Self {
+        Error::WebDriver(Box::new(e))
+    }
+}
+
+/// Reason a single video failed to download.
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadError {
+    #[error("No urls captured")]
+    NoUrls,
+    #[error("Unexpected stream url: {0}")]
+    BadUrl(String),
+    #[error("Interrupted before download has finished")]
+    Interrupted,
+    #[error("Stream url has expired or access is denied")]
+    Expired,
+    #[error("Failed to run ffmpeg: {0}")]
+    Spawn(std::io::Error),
+    /// `message` is the last error ffmpeg printed
+    #[error("ffmpeg {status}: {message}")]
+    Ffmpeg { status: ExitStatus, message: String },
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
@@ -0,0 +1,214 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::{DownloadError, Error};
+use crate::video_saver::VideoInfo;
+
+/// File failed videos are kept in, in the output directory by default.
+pub const FAILED_VIDEOS_FILE: &str = "failed_videos_data.json";
+
+/// Why the video failed to download, used to pick videos to retry.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    Serialize,
+    Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailureKind {
+    /// No player urls were captured
+    NoUrls,
+    /// Captured url is not a known stream url
+    BadUrl,
+    /// ffmpeg could not be started
+    Spawn,
+    /// Signed stream url has expired, it is captured again on retry
+    Expired,
+    /// ffmpeg exited with an error
+    Ffmpeg,
+    /// Output file could not be written
+    Io,
+    /// Run was stopped before the video was downloaded
+    Interrupted,
+    /// Written by an older version without failure reasons
+    #[default]
+    Unknown,
+}
+
+impl DownloadError {
+    pub fn kind(&self) -> FailureKind {
+        match self {
+            DownloadError::NoUrls => FailureKind::NoUrls,
+            DownloadError::BadUrl(_) => FailureKind::BadUrl,
+            DownloadError::Interrupted => FailureKind::Interrupted,
+            DownloadError::Expired => FailureKind::Expired,
+            DownloadError::Spawn(_) => FailureKind::Spawn,
+            DownloadError::Ffmpeg { .. } => FailureKind::Ffmpeg,
+            DownloadError::Io(_) => FailureKind::Io,
+        }
+    }
+}
+
+/// Entry of the failed videos file. It is a superset of [`VideoInfo`], so
+/// the file can be passed to `--path-to-videos-info-file` as well.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FailedVideo {
+    #[serde(flatten)]
+    pub info: VideoInfo,
+    #[serde(default)]
+    pub kind: FailureKind,
+    #[serde(default)]
+    pub message: String,
+    #[serde(default = "first_attempt")]
+    pub attempts: u32,
+    #[serde(default, with = "time::serde::rfc3339::option")]
+    pub failed_at: Option<OffsetDateTime>,
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+/// Failed videos collected over all runs.
+pub struct FailedVideos {
+    path: PathBuf,
+    entries: Vec<FailedVideo>,
+}
+
+impl FailedVideos {
+    /// Load failures of previous runs, the file may not exist yet.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let entries = if path.exists() {
+            serde_json::from_str(&read_to_string(path)?).map_err(|e| {
+                Error::Config(format!(
+                    "Failed to parse {}: {e}",
+                    path.display()
+                ))
+            })?
+        } else {
+            Vec::new()
+        };
+        Ok(FailedVideos {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> &[FailedVideo] {
+        &self.entries
+    }
+
+    /// Videos to retry, failed with one of `kinds` or all if it is empty.
+    pub fn select(&self, kinds: &[FailureKind]) -> Vec<VideoInfo> {
+        self.entries
+            .iter()
+            .filter(|entry| kinds.is_empty() || kinds.contains(&entry.kind))
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    /// Update entries with the results of a run. Videos from `attempted`
+    /// which are not among `failures` have been downloaded and are dropped,
+    /// failed ones get their attempt counted.
+    pub fn record(
+        &mut self,
+        attempted: &[VideoInfo],
+        failures: &[(VideoInfo, DownloadError)],
+    ) {
+        let now = OffsetDateTime::now_utc();
+        let mut previous = std::mem::take(&mut self.entries);
+        for (info, error) in failures {
+            // Interrupted download doesn't count as an attempt
+            let attempt = match error {
+                DownloadError::Interrupted => 0,
+                _ => 1,
+            };
+            let attempts =
+                match previous.iter().position(|e| e.info.same_video(info)) {
+                    Some(idx) => previous.remove(idx).attempts + attempt,
+                    None => attempt,
+                };
+            self.entries.push(FailedVideo {
+                info: info.clone(),
+                kind: error.kind(),
+                message: error.to_string(),
+                attempts,
+                failed_at: Some(now),
+            });
+        }
+        previous.retain(|entry| {
+            !attempted.iter().any(|info| info.same_video(&entry.info))
+        });
+        previous.append(&mut self.entries);
+        self.entries = previous;
+    }
+
+    /// Write entries back, the file is removed once nothing has failed.
+    pub fn save(&self) -> Result<(), Error> {
+        if self.entries.is_empty() {
+            if self.path.exists() {
+                std::fs::remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        let json =
+            serde_json::to_string_pretty(&self.entries).map_err(|e| {
+                Error::Internal(format!(
+                    "Failed to serialize failed videos data: {e}"
+                ))
+            })?;
+        std::fs::write(&self.path, json)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FailedVideos, FailureKind};
+    use crate::error::DownloadError;
+    use crate::video_saver::VideoInfo;
+
+    #[test]
+    fn merges_failures_across_runs() {
+        let mut failed = FailedVideos {
+            path: "unused.json".into(),
+            entries: Vec::new(),
+        };
+        failed.record(
+            &[],
+            &[
+                (VideoInfo::test("a"), DownloadError::NoUrls),
+                (VideoInfo::test("b"), DownloadError::NoUrls),
+            ],
+        );
+        // Retry of `a` fails again, `b` is downloaded
+        failed.record(
+            &[VideoInfo::test("a"), VideoInfo::test("b")],
+            &[(VideoInfo::test("a"), DownloadError::BadUrl("x".to_string()))],
+        );
+        failed.record(&[], &[(VideoInfo::test("c"), DownloadError::NoUrls)]);
+
+        let entries = failed.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].info, VideoInfo::test("a"));
+        assert_eq!(entries[0].attempts, 2);
+        assert_eq!(entries[0].kind, FailureKind::BadUrl);
+        assert_eq!(entries[1].info, VideoInfo::test("c"));
+        assert_eq!(
+            failed.select(&[FailureKind::NoUrls]),
+            vec![VideoInfo::test("c")]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::shutdown::background_command;
+
+/// ffmpeg copying the stream at `url` into mp4 file `output`, overwriting
+/// it. Progress is written to stdout, warnings and errors to stderr, each
+/// line tagged with its level.
+pub fn command(url: &str, output: &Path) -> Command {
+    let mut command = background_command("ffmpeg");
+    command
+        .args([
+            "-protocol_whitelist",
+            "file,http,https,tcp,tls",
+            // Rejected requests are logged as warnings, the error is only
+            // about the missing input
+            "-loglevel",
+            "level+warning",
+            "-nostats",
+            "-progress",
+            "pipe:1",
+            "-i",
+            url,
+            "-c",
+            "copy",
+            "-f",
+            "mp4",
+            "-y",
+        ])
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    command
+}
+
+/// How ffmpeg has exited and the warnings and errors it printed.
+pub struct Finished {
+    pub status: ExitStatus,
+    pub log: Vec<String>,
+}
+
+impl Finished {
+    /// Signed url was rejected, it has to be captured again.
+    pub fn forbidden(&self) -> bool {
+        self.log.iter().any(|line| is_forbidden(line))
+    }
+
+    /// Failure message, ffmpeg prints the fatal error last.
+    pub fn message(&self) -> String {
+        self.log
+            .iter()
+            .rev()
+            .find_map(|line| {
+                ["[error] ", "[fatal] "]
+                    .iter()
+                    .find(|level| line.contains(*level))
+                    .map(|level| line.replacen(level, "", 1))
+            })
+            .unwrap_or_else(|| "no error output".to_string())
+    }
+
+    pub fn write_log(&self, path: &Path) -> std::io::Result<()> {
+        let mut log = self.log.join("\n");
+        log.push('\n');
+        std::fs::write(path, log)
+    }
+}
+
+/// Read output of `child` started from [`command`], passing progress to
+/// `on_progress`, and wait for it to exit.
+pub async fn wait(
+    child: &mut Child,
+    on_progress: impl Fn(&ProgressUpdate),
+) -> std::io::Result<Finished> {
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let read_progress = async {
+        if let Some(stdout) = stdout {
+            let mut parser = ProgressParser::default();
+            let mut lines = BufReader::new(stdout).lines();
+            while let Some(line) = lines.next_line().await? {
+                if let Some(update) = parser.feed(&line) {
+                    on_progress(&update);
+                }
+            }
+        }
+        Ok::<_, std::io::Error>(())
+    };
+    let read_log = async {
+        let mut log = Vec::new();
+        if let Some(stderr) = stderr {
+            let mut lines = BufReader::new(stderr).lines();
+            while let Some(line) = lines.next_line().await? {
+                if !line.trim().is_empty() {
+                    log.push(line);
+                }
+            }
+        }
+        Ok(log)
+    };
+    let ((), log) = tokio::try_join!(read_progress, read_log)?;
+    Ok(Finished {
+        status: child.wait().await?,
+        log,
+    })
+}
+
+/// ffmpeg reports http errors like `HTTP error 403 Forbidden` and
+/// `Server returned 403 Forbidden`.
+fn is_forbidden(line: &str) -> bool {
+    ["401", "403"].iter().any(|code| {
+        line.contains(&format!("Server returned {code}"))
+            || line.contains(&format!("HTTP error {code}"))
+    })
+}
+
+/// State of a download, ffmpeg writes one block of `key=value` lines
+/// with `-progress` every half a second.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProgressUpdate {
+    /// Position in the video written so far
+    pub out_time: Duration,
+    /// Bytes written so far
+    pub total_size: u64,
+    /// Multiple of realtime, unknown at the start
+    pub speed: Option<f64>,
+    /// Last block, ffmpeg is done
+    pub end: bool,
+}
+
+/// Collects `-progress` lines into updates.
+#[derive(Default)]
+pub struct ProgressParser {
+    current: ProgressUpdate,
+}
+
+impl ProgressParser {
+    /// Feed a line, returns the update once its block is complete.
+    pub fn feed(&mut self, line: &str) -> Option<ProgressUpdate> {
+        let (key, value) = line.split_once('=')?;
+        let value = value.trim();
+        match key.trim() {
+            "out_time_us" => {
+                if let Ok(us) = value.parse() {
+                    self.current.out_time = Duration::from_micros(us);
+                }
+            }
+            "total_size" => {
+                if let Ok(size) = value.parse() {
+                    self.current.total_size = size;
+                }
+            }
+            "speed" => {
+                self.current.speed = value
+                    .strip_suffix('x')
+                    .and_then(|speed| speed.trim().parse().ok());
+            }
+            "progress" => {
+                self.current.end = value == "end";
+                return Some(self.current.clone());
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Ask ffprobe for the length of the video, `None` if it can't tell.
+pub async fn probe_duration(url: &str) -> Option<Duration> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-protocol_whitelist",
+            "file,http,https,tcp,tls",
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            url,
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let seconds: f64 = String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .ok()?;
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitStatus;
+    use std::time::Duration;
+
+    use super::{is_forbidden, Finished, ProgressParser};
+
+    #[test]
+    fn parses_progress_blocks() {
+        let output = "frame=0\nout_time_us=N/A\ntotal_size=48\nspeed=N/A\n\
+            progress=continue\nout_time_us=2500000\ntotal_size=1024\n\
+            speed= 1.5x\nprogress=end\n";
+        let mut parser = ProgressParser::default();
+        let updates: Vec<_> =
+            output.lines().filter_map(|l| parser.feed(l)).collect();
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].speed, None);
+        assert!(!updates[0].end);
+        assert_eq!(updates[1].out_time, Duration::from_millis(2500));
+        assert_eq!(updates[1].total_size, 1024);
+        assert_eq!(updates[1].speed, Some(1.5));
+        assert!(updates[1].end);
+    }
+
+    #[test]
+    fn detects_rejected_signed_url() {
+        assert!(is_forbidden("[https @ 0x1] HTTP error 403 Forbidden"));
+        assert!(is_forbidden(
+            "a.m3u8: Server returned 403 Forbidden (access denied)"
+        ));
+        assert!(!is_forbidden("a.m3u8: Server returned 404 Not Found"));
+
+        let finished = Finished {
+            status: ExitStatus::default(),
+            log: vec![
+                "[https @ 0x1] [warning] HTTP error 403 Forbidden".to_string(),
+                "[in#0 @ 0x2] [error] Error opening input: Forbidden"
+                    .to_string(),
+                "[warning] Error opening input file a.m3u8.".to_string(),
+            ],
+        };
+        assert!(finished.forbidden());
+        assert_eq!(
+            finished.message(),
+            "[in#0 @ 0x2] Error opening input: Forbidden"
+        );
+    }
+}
@@ -0,0 +1,311 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::report::{
+    escape, format_secs, href, Outcome, PageReport, RunReport, VideoReport,
+};
+
+/// Index of lessons, written into each course directory.
+pub const COURSE_INDEX_FILE: &str = "index.html";
+/// Playlist of lessons, written into each section directory.
+pub const SECTION_PLAYLIST_FILE: &str = "playlist.m3u";
+
+/// Lessons of all runs, merged so that runs which only download videos
+/// update the indexes too.
+pub const LESSONS_FILE: &str = "lessons.json";
+
+/// Pages of one course. The breadcrumb starts with the root page, which
+/// lists the courses, so the course is its second page when there is one.
+struct Course<'a> {
+    path: &'a [String],
+    dir: PathBuf,
+    lessons: Vec<&'a PageReport>,
+}
+
+impl Course<'_> {
+    fn title(&self) -> &str {
+        self.path.last().map_or("", String::as_str)
+    }
+
+    /// Lessons grouped by section, sections in the order of their first
+    /// lesson. Sessions visit pages in any order, so lessons of a section
+    /// don't have to follow each other.
+    fn sections(&self) -> Vec<(&[String], Vec<&PageReport>)> {
+        let mut sections: Vec<(&[String], Vec<&PageReport>)> = Vec::new();
+        for lesson in &self.lessons {
+            let section = &lesson.breadcrumb[self.path.len()..];
+            match sections.iter_mut().find(|(s, _)| *s == section) {
+                Some((_, lessons)) => lessons.push(lesson),
+                None => sections.push((section, vec![lesson])),
+            }
+        }
+        sections
+    }
+}
+
+/// Merge lessons of the run into [`LESSONS_FILE`] and write an index of
+/// every course found there, linking downloaded videos in crawl order,
+/// and a playlist per section if `playlists` is set.
+pub fn write_indexes(
+    report: &RunReport,
+    output_dir: &Path,
+    playlists: bool,
+) -> Result<(), Error> {
+    let lessons_file = output_dir.join(LESSONS_FILE);
+    let mut lessons = read_lessons(&lessons_file)?;
+    merge(&mut lessons, report);
+    lessons.retain(|lesson| !lesson.videos.is_empty());
+    if lessons.is_empty() {
+        return Ok(());
+    }
+    let json = serde_json::to_string_pretty(&lessons).map_err(|e| {
+        Error::Internal(format!("Failed to serialize lessons: {e}"))
+    })?;
+    std::fs::create_dir_all(output_dir)?;
+    std::fs::write(&lessons_file, json)?;
+    lessons.sort_by(|a, b| a.order.cmp(&b.order));
+
+    let mut courses: Vec<Course> = Vec::new();
+    for page in &lessons {
+        if page.breadcrumb.is_empty() {
+            continue;
+        }
+        let path = &page.breadcrumb[..page.breadcrumb.len().min(2)];
+        match courses.iter_mut().find(|course| course.path == path) {
+            Some(course) => course.lessons.push(page),
+            None => courses.push(Course {
+                path,
+                dir: output_dir.join(path.iter().collect::<PathBuf>()),
+                lessons: vec![page],
+            }),
+        }
+    }
+    for course in &courses {
+        std::fs::create_dir_all(&course.dir)?;
+        std::fs::write(
+            course.dir.join(COURSE_INDEX_FILE),
+            render_index(course),
+        )?;
+        if playlists {
+            write_playlists(course)?;
+        }
+        tracing::info!(
+            "Course index is written to {}",
+            course.dir.join(COURSE_INDEX_FILE).display()
+        );
+    }
+    Ok(())
+}
+
+fn read_lessons(path: &Path) -> Result<Vec<PageReport>, Error> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    serde_json::from_str(&std::fs::read_to_string(path)?).map_err(|e| {
+        Error::Internal(format!("Bad lessons file {}: {e}", path.display()))
+    })
+}
+
+/// Merge pages and videos of the run into lessons of earlier runs. Videos
+/// of pages not visited by the run, e.g. retried ones, go to the lesson
+/// they were found on.
+fn merge(lessons: &mut Vec<PageReport>, report: &RunReport) {
+    for page in &report.pages {
+        match lessons.iter_mut().find(|lesson| lesson.url == page.url) {
+            Some(lesson) => {
+                lesson.order.clone_from(&page.order);
+                lesson.breadcrumb.clone_from(&page.breadcrumb);
+                lesson.title.clone_from(&page.title);
+            }
+            None => lessons.push(PageReport {
+                videos: Vec::new(),
+                ..page.clone()
+            }),
+        }
+    }
+    for video in report
+        .pages
+        .iter()
+        .flat_map(|page| &page.videos)
+        .chain(&report.other_videos)
+    {
+        merge_video(lessons, video);
+    }
+}
+
+/// A video downloaded by an earlier run stays linked, unless this run has
+/// downloaded it again.
+fn merge_video(lessons: &mut [PageReport], video: &VideoReport) {
+    let existing = lessons
+        .iter_mut()
+        .flat_map(|lesson| lesson.videos.iter_mut())
+        .find(|v| v.info.same_video(&video.info));
+    if let Some(existing) = existing {
+        if video.outcome == Outcome::Downloaded
+            || existing.outcome != Outcome::Downloaded
+        {
+            *existing = video.clone();
+        }
+        return;
+    }
+    let lesson = video.info.source.as_ref().and_then(|source| {
+        lessons.iter_mut().find(|lesson| lesson.url == source.page)
+    });
+    if let Some(lesson) = lesson {
+        lesson.videos.push(video.clone());
+    }
+}
+
+/// Downloaded files of the lesson with their durations.
+fn files(lesson: &PageReport) -> Vec<(&Path, Option<f64>)> {
+    lesson
+        .videos
+        .iter()
+        .filter(|video| video.outcome == Outcome::Downloaded)
+        .filter_map(|video| Some((video.file.as_deref()?, video.duration_secs)))
+        .collect()
+}
+
+fn render_index(course: &Course) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>{0}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n\
+        <h1>{0}</h1>\n",
+        escape(course.title())
+    );
+    for (section, lessons) in course.sections() {
+        // Videos of the lesson are saved in the directory of its section
+        if !section.is_empty() {
+            let _ = writeln!(html, "<h2>{}</h2>", escape(&section.join(" / ")));
+        }
+        html.push_str("<ol>\n");
+        for lesson in lessons {
+            render_lesson(&mut html, course, lesson);
+        }
+        html.push_str("</ol>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_lesson(html: &mut String, course: &Course, lesson: &PageReport) {
+    let _ = write!(html, "<li>{}", escape(&lesson.title));
+    let files = files(lesson);
+    if files.is_empty() {
+        html.push_str(" <span class=\"missing\">not downloaded</span>");
+    }
+    for (part, (file, duration)) in files.iter().enumerate() {
+        let link = file.strip_prefix(&course.dir).unwrap_or(file);
+        let _ =
+            write!(html, " <a href=\"{}\">video {}</a>", href(link), part + 1);
+        if let Some(secs) = duration {
+            let _ = write!(html, " ({})", format_secs(*secs));
+        }
+    }
+    html.push_str("</li>\n");
+}
+
+/// Playlist per section directory, its lessons in crawl order.
+fn write_playlists(course: &Course) -> Result<(), Error> {
+    let mut playlists: Vec<(&Path, String)> = Vec::new();
+    for lesson in &course.lessons {
+        let files = files(lesson);
+        for (part, (file, duration)) in files.iter().enumerate() {
+            let (Some(dir), Some(name)) = (file.parent(), file.file_name())
+            else {
+                continue;
+            };
+            let idx = match playlists.iter().position(|(d, _)| *d == dir) {
+                Some(idx) => idx,
+                None => {
+                    playlists.push((dir, "#EXTM3U\n".to_string()));
+                    playlists.len() - 1
+                }
+            };
+            let title = if files.len() > 1 {
+                format!("{} ({})", lesson.title, part + 1)
+            } else {
+                lesson.title.clone()
+            };
+            let _ = write!(
+                playlists[idx].1,
+                "#EXTINF:{},{title}\n{}\n",
+                duration.map_or(-1, |secs| secs.round() as i64),
+                name.to_string_lossy()
+            );
+        }
+    }
+    for (dir, playlist) in playlists {
+        std::fs::write(dir.join(SECTION_PLAYLIST_FILE), playlist)?;
+    }
+    Ok(())
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+h2 { margin-top: 1.5em; }
+li { margin: 0.3em 0; }
+.missing { color: #999; }";
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::write_indexes;
+    use crate::report::Report;
+    use crate::video_saver::VideoInfo;
+
+    #[test]
+    fn links_lessons_in_crawl_order() {
+        let out = std::env::temp_dir()
+            .join(format!("video_downloader_index_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&out);
+        let report = Report::new();
+        // Visited by parallel sessions, out of crawl order
+        let lessons = [
+            ("a", [0, 1], "Part 1", "Next"),
+            ("c", [1, 0], "Part 2", "Other"),
+            ("b", [0, 0], "Part 1", "Intro"),
+        ];
+        for (lesson, order, part, title) in lessons {
+            let section = out.join("School/Course").join(part);
+            std::fs::create_dir_all(&section).unwrap();
+            let breadcrumb = ["School", "Course", part].map(String::from);
+            let url = format!("https://example.com/{lesson}");
+            report.page_visited(&url, &order, &breadcrumb, title);
+            let info = VideoInfo::test(&format!("School/Course/{part}"))
+                .found_on(&url, 0);
+            let file = section.join(format!("{lesson}.mp4"));
+            std::fs::write(&file, "video").unwrap();
+            report.video_queued(&info);
+            report.video_downloaded(
+                &info,
+                &file,
+                Some(Duration::from_secs(61)),
+            );
+        }
+
+        write_indexes(&report.finish(), &out, true).unwrap();
+        // Lessons of earlier runs are kept by a run which crawled nothing
+        write_indexes(&Report::new().finish(), &out, true).unwrap();
+        let index =
+            std::fs::read_to_string(out.join("School/Course/index.html"))
+                .unwrap();
+        let playlist = std::fs::read_to_string(
+            out.join("School/Course/Part 1/playlist.m3u"),
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&out).unwrap();
+        assert!(index.contains("<h1>Course</h1>"));
+        assert_eq!(index.matches("<h2>Part 1</h2>").count(), 1);
+        assert!(index.contains("<li>Intro <a href=\"Part%201/b.mp4\">"));
+        assert!(index.find("Intro") < index.find("Next"));
+        assert!(index.find("Next") < index.find("Other"));
+        assert_eq!(
+            playlist,
+            "#EXTM3U\n#EXTINF:61,Intro\nb.mp4\n#EXTINF:61,Next\na.mp4\n"
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::fs::{read_to_string, File};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::error::SendError;
+use tokio::sync::mpsc::Sender;
+
+use crate::credentials::prompt;
+use crate::error::Error;
+use crate::print_err;
+use crate::progress::Progress;
+use crate::report::Report;
+use crate::video_saver::VideoInfo;
+
+/// Journal of the download queue, in the output directory by default.
+pub const QUEUE_JOURNAL_FILE: &str = "download_queue.jsonl";
+
+/// What to do with downloads left unfinished by a crashed run.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Resume {
+    /// Ask on the terminal, same as `no` if there is no terminal
+    Ask,
+    /// Download them before doing anything else
+    Yes,
+    /// Keep them in the failed videos file for `retry`
+    No,
+}
+
+impl Resume {
+    /// Decide whether `count` unfinished downloads should be continued.
+    pub fn confirm(self, count: usize) -> Result<bool, Error> {
+        match self {
+            Resume::Yes => Ok(true),
+            Resume::No => Ok(false),
+            Resume::Ask if !std::io::stdin().is_terminal() => {
+                tracing::warn!(
+                    "Found {count} unfinished downloads, use `--resume yes` \
+                    to continue them"
+                );
+                Ok(false)
+            }
+            Resume::Ask => {
+                let answer = prompt(&format!(
+                    "Found {count} unfinished downloads of the previous run, \
+                    continue them? [Y/n] "
+                ))?;
+                Ok(!answer.eq_ignore_ascii_case("n")
+                    && !answer.eq_ignore_ascii_case("no"))
+            }
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+enum Event {
+    Queued { video: VideoInfo },
+    Finished { video: VideoInfo },
+}
+
+/// Append-only log of the download queue. Every video is written down
+/// before it is queued and once it is downloaded or recorded as failed,
+/// so the queue survives a crash.
+#[derive(Clone)]
+pub struct Journal {
+    file: Arc<Mutex<File>>,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> Result<Self, Error> {
+        let mut file = File::options().create(true).append(true).open(path)?;
+        // Terminate the line cut by a crash, so new events stay readable
+        if std::fs::read(path)?.last().is_some_and(|b| *b != b'\n') {
+            file.write_all(b"\n")?;
+        }
+        Ok(Journal {
+            file: Arc::new(Mutex::new(file)),
+        })
+    }
+
+    /// Videos queued but not finished by the run which wrote the journal.
+    pub fn pending(path: &Path) -> Result<Vec<VideoInfo>, Error> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let mut pending: Vec<VideoInfo> = Vec::new();
+        for line in read_to_string(path)?.lines() {
+            // Last line is cut if the process died while writing it
+            let Ok(event) = serde_json::from_str(line) else {
+                tracing::warn!("Skipping broken journal line: {line}");
+                continue;
+            };
+            match event {
+                Event::Queued { video } => {
+                    if !pending.iter().any(|v| v.same_video(&video)) {
+                        pending.push(video);
+                    }
+                }
+                Event::Finished { video } => {
+                    pending.retain(|v| !v.same_video(&video));
+                }
+            }
+        }
+        Ok(pending)
+    }
+
+    pub fn queued(&self, video: &VideoInfo) -> Result<(), Error> {
+        self.write(&Event::Queued {
+            video: video.clone(),
+        })
+    }
+
+    pub fn finished(&self, video: &VideoInfo) -> Result<(), Error> {
+        self.write(&Event::Finished {
+            video: video.clone(),
+        })
+    }
+
+    /// Forget everything, called once the outcome of all queued videos is
+    /// saved elsewhere.
+    pub fn clear(&self) -> Result<(), Error> {
+        self.file.lock().unwrap().set_len(0)?;
+        Ok(())
+    }
+
+    fn write(&self, event: &Event) -> Result<(), Error> {
+        let mut line = serde_json::to_string(event).map_err(|e| {
+            Error::Internal(format!("Failed to serialize journal event: {e}"))
+        })?;
+        line.push('\n');
+        // Single write, so lines of concurrent writers don't interleave
+        self.file.lock().unwrap().write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Sending half of the download queue, videos are journaled before they
+/// are queued.
+#[derive(Clone)]
+pub struct QueueSender {
+    tx: Sender<VideoInfo>,
+    journal: Journal,
+    queued: Arc<Mutex<Vec<VideoInfo>>>,
+    progress: Arc<Progress>,
+    report: Arc<Report>,
+}
+
+impl QueueSender {
+    /// Sender which counts and reports queued videos in `progress` and
+    /// `report`.
+    pub fn new(
+        tx: Sender<VideoInfo>,
+        journal: Journal,
+        progress: Arc<Progress>,
+        report: Arc<Report>,
+    ) -> Self {
+        QueueSender {
+            tx,
+            journal,
+            queued: Arc::default(),
+            progress,
+            report,
+        }
+    }
+
+    /// Videos queued so far by all clones of the sender.
+    pub fn queued(&self) -> Vec<VideoInfo> {
+        self.queued.lock().unwrap().clone()
+    }
+
+    pub async fn send(
+        &self,
+        video: VideoInfo,
+    ) -> Result<(), SendError<VideoInfo>> {
+        print_err!(self.journal.queued(&video), ());
+        self.report.video_queued(&video);
+        self.queued.lock().unwrap().push(video.clone());
+        self.tx.send(video).await?;
+        self.progress.video_queued();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Journal;
+    use crate::video_saver::VideoInfo;
+
+    #[test]
+    fn pending_survives_broken_tail() {
+        let path = std::env::temp_dir().join(format!(
+            "video_downloader_journal_{}.jsonl",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let video = VideoInfo::test;
+
+        let journal = Journal::open(&path).unwrap();
+        journal.queued(&video("a")).unwrap();
+        journal.queued(&video("b")).unwrap();
+        journal.finished(&video("a")).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| {
+                std::io::Write::write_all(&mut f, b"{\"event\":\"fin")
+            })
+            .unwrap();
+        // Resumed after a crash
+        let journal = Journal::open(&path).unwrap();
+        journal.queued(&video("b")).unwrap();
+        journal.queued(&video("c")).unwrap();
+        journal.finished(&video("c")).unwrap();
+
+        let pending = Journal::pending(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(pending, vec![video("b")]);
+    }
+}
@@ -0,0 +1,49 @@
+pub mod browser_capture;
+pub mod cli;
+pub mod config;
+pub mod cookies;
+pub mod credentials;
+pub mod error;
+pub mod failed;
+pub mod ffmpeg;
+pub mod index;
+pub mod journal;
+pub mod logging;
+pub mod profile;
+pub mod progress;
+pub mod proxy;
+pub mod report;
+pub mod run;
+pub mod shutdown;
+pub mod site;
+pub mod video_saver;
+
+pub use cli::Args;
+pub use error::Error;
+pub use run::{CrawlOptions, Crawler};
+pub use video_saver::{VideoInfo, VideoSaver};
+
+/// This macro is for tracing error and returning Result if there are some
+/// meaningful Ok() case, and returning () if there are no meaningful result.
+/// It is useful to simply trace error message on fallible operations which doesn't
+/// return anything in the Ok() branch.
+#[macro_export]
+macro_rules! print_err {
+    ($exp:expr) => {
+        match $exp {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                tracing::error!("{e}");
+                Err(e)
+            }
+        }
+    };
+    ($exp:expr, ()) => {
+        match $exp {
+            Ok(()) => (),
+            Err(e) => {
+                tracing::error!("{e}");
+            }
+        }
+    };
+}
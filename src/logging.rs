@@ -0,0 +1,108 @@
+use std::fs::File;
+use std::io::IsTerminal;
+
+use serde::{Deserialize, Serialize};
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer, Registry};
+
+use crate::progress::LogWriter;
+use crate::Args;
+
+/// Format of log lines, on the terminal and in the log file.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, Serialize, Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// Json object per line, with fields of all enclosing spans
+    Json,
+}
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Set up logging to the terminal through `terminal` and the log file.
+/// Failing to open the log file is logged, the run goes on without it.
+pub fn init(args: &Args, terminal: LogWriter) {
+    let filter = EnvFilter::from_default_env()
+        .add_directive("hudsucker=off".parse().unwrap())
+        .add_directive(
+            format!("video_downloader={}", level(args.verbose, args.quiet))
+                .parse()
+                .unwrap(),
+        );
+    let mut layers = vec![layer(
+        args.log_format,
+        terminal,
+        Target::Terminal {
+            ansi: std::io::stdout().is_terminal(),
+        },
+    )];
+    let mut file_error = None;
+    if !args.no_log_file {
+        match File::options()
+            .create(true)
+            .append(true)
+            .open(&args.log_file)
+        {
+            Ok(file) => layers.push(layer(args.log_format, file, Target::File)),
+            Err(e) => file_error = Some(e),
+        }
+    }
+    if let Err(e) = tracing_subscriber::registry()
+        .with(layers)
+        .with(filter)
+        .try_init()
+    {
+        eprintln!("Failed to set up logging: {e}");
+    }
+    if let Some(e) = file_error {
+        tracing::warn!(
+            "Can't open log file {}, logging to terminal only: {e}",
+            args.log_file.display()
+        );
+    }
+}
+
+/// Where a layer writes, the terminal has no timestamps and colors only
+/// if stdout isn't redirected.
+#[derive(Clone, Copy)]
+enum Target {
+    Terminal { ansi: bool },
+    File,
+}
+
+fn layer<W>(format: LogFormat, writer: W, target: Target) -> BoxedLayer
+where
+    W: for<'a> tracing_subscriber::fmt::MakeWriter<'a> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(matches!(target, Target::Terminal { ansi: true }));
+    match (format, target) {
+        (LogFormat::Json, _) => layer
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .boxed(),
+        (LogFormat::Text, Target::Terminal { .. }) => {
+            layer.without_time().boxed()
+        }
+        (LogFormat::Text, Target::File) => layer.boxed(),
+    }
+}
+
+/// Level of our own logs, `-v` and `-q` move it from info one step each.
+fn level(verbose: u8, quiet: u8) -> LevelFilter {
+    match i16::from(verbose) - i16::from(quiet) {
+        ..=-3 => LevelFilter::OFF,
+        -2 => LevelFilter::ERROR,
+        -1 => LevelFilter::WARN,
+        0 => LevelFilter::INFO,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    }
+}
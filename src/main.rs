@@ -1,99 +1,76 @@
-use std::path::PathBuf;
+use std::sync::Arc;
 
-use clap::Parser;
-use tracing_subscriber::{filter::FilterExt, layer::SubscriberExt};
+use clap::{CommandFactory, FromArgMatches};
+use video_downloader::cli::{Args, Command, ConfigCommand};
+use video_downloader::profile::Profile;
+use video_downloader::progress::Progress;
+use video_downloader::run::read_videos_info;
+use video_downloader::shutdown::Shutdown;
+use video_downloader::{config, logging, Crawler, Error};
 
-pub mod pingora_proxy;
-pub mod proxy;
-mod run;
-pub mod video_saver;
-
-/// This macro is for tracing error and returning Result if there are some
-/// meaningful Ok() case, and returning () if there are no meaningful result.
-/// It is useful to simply trace error message on fallible operations which doesn't
-/// return anything in the Ok() branch.
-#[macro_export]
-macro_rules! print_err {
-    ($exp:expr) => {
-        match $exp {
-            Ok(v) => Ok(v),
+#[tokio::main]
+async fn main() {
+    let matches = Args::command().get_matches();
+    let mut args =
+        Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    // Logging is set up by the merged config, report errors before it
+    if let Some(path) = args.config.clone().or_else(config::find_config_file) {
+        args = match config::apply(args, &matches, &path) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("Error: {e}");
+                std::process::exit(e.exit_code());
+            }
+        };
+    }
+    if let Some(name) = &args.profile {
+        match Profile::load(&args.profiles_dir.join(name)) {
+            Ok(profile) => profile.apply(&mut args, &matches),
             Err(e) => {
-                tracing::error!("{e}");
-                Err(e)
+                eprintln!("Error: failed to load profile {name}: {e}");
+                std::process::exit(e.exit_code());
             }
         }
-    };
-    ($exp:expr, ()) => {
-        match $exp {
-            Ok(()) => (),
+    }
+    // Printed before logging is set up, so the output is plain toml and
+    // no log file is created
+    if let Some(Command::Config {
+        command: ConfigCommand::Show,
+    }) = args.command
+    {
+        match toml::to_string_pretty(&args) {
+            Ok(s) => print!("{s}"),
             Err(e) => {
-                tracing::error!("{e}");
-                ()
+                let e =
+                    Error::Config(format!("Failed to serialize config: {e}"));
+                eprintln!("Error: {e}");
+                std::process::exit(e.exit_code());
             }
         }
-    };
-}
-
-/// Robot to download medical videos
-#[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
-struct Args {
-    /// Authentication email
-    #[arg(short, long)]
-    email: String,
-
-    /// Authentication password
-    #[arg(short, long)]
-    password: String,
-
-    #[arg(short, long, default_value_t = String::from("http://localhost:4444"))]
-    geckodriver_address: String,
-
-    #[arg(short, long, default_value_t = 8080)]
-    proxy_port: u16,
-
-    #[arg(short, long, default_value_t = String::from("/teach/control"))]
-    base: String,
-    #[arg(short, long, default_value_t = String::from("/teach/control/stream/index"))]
-    root: String,
-    #[arg(short, long, default_value_t = String::from("https://universkill.ru"))]
-    domain: String,
-    #[arg(short, long, default_value_t = String::from("https://universkill.ru/cms/system/login?required=true"))]
-    auth_url: String,
-
-    /// If you need to download only specified videos
-    #[arg(short, long)]
-    path_to_videos_info_file: Option<PathBuf>,
-}
-
-fn main() {
-    init_tracing_subscriber();
+        return;
+    }
+    let progress = Arc::new(Progress::terminal());
+    logging::init(&args, progress.log_writer());
     tracing::info!("Hello world!");
-    let args = Args::parse();
-    run::run(args).unwrap();
-}
-
-fn init_tracing_subscriber() {
-    let subscriber = tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .without_time()
-        .with_level(true)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::from_default_env()
-                .add_directive("hudsucker=off".parse().unwrap())
-                .add_directive("video_downloader=info".parse().unwrap()),
-        )
-        .finish()
-        .with(
-            tracing_subscriber::fmt::layer().with_writer(
-                std::fs::File::options()
-                    .create(true)
-                    .append(true)
-                    .write(true)
-                    .open("downloader.log")
-                    .expect("Can't open log file!"),
-            ),
-        );
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("Failed to set up tracing");
+    let shutdown = Shutdown::new();
+    shutdown.listen_ctrl_c();
+    let crawler = Crawler::new(args.crawl_options(), shutdown.clone())
+        .with_progress(progress.clone());
+    let run = async {
+        match (&args.command, &args.path_to_videos_info_file) {
+            (Some(Command::Retry { kind, .. }), _) => crawler.retry(kind).await,
+            (_, Some(path)) => crawler.download(read_videos_info(path)?).await,
+            _ => crawler.crawl().await,
+        }
+    };
+    // Children are killed by now, pending videos stay in the journal
+    let result = tokio::select! {
+        result = run => result,
+        () = shutdown.forced() => Err(Error::Interrupted),
+    };
+    progress.clear();
+    if let Err(e) = result {
+        tracing::error!("{e}");
+        std::process::exit(e.exit_code());
+    }
 }
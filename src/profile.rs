@@ -0,0 +1,115 @@
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
+
+use clap::parser::ValueSource;
+use clap::ArgMatches;
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::run::auth::LoginCheck;
+use crate::Args;
+
+const PROFILE_FILE: &str = "profile.toml";
+const COOKIE_FILE: &str = "cookie.txt";
+const CREDENTIALS_FILE: &str = "credentials";
+
+/// Account profile living in its own directory, `<profiles-dir>/<name>`.
+/// Directory keeps the account cookie jar, optional credentials file and
+/// `profile.toml` with site settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    #[serde(skip)]
+    dir: PathBuf,
+    pub email: Option<String>,
+    pub domain: Option<String>,
+    pub base: Option<String>,
+    pub root: Option<String>,
+    pub auth_url: Option<String>,
+    pub output_dir: Option<PathBuf>,
+    pub login_email_selector: Option<String>,
+    pub login_password_selector: Option<String>,
+    pub login_submit_selector: Option<String>,
+    pub login_success: Option<Vec<LoginCheck>>,
+}
+
+impl Profile {
+    /// Load profile from its directory, which must exist.
+    /// `profile.toml` is optional.
+    pub fn load(dir: &Path) -> Result<Self, Error> {
+        if !dir.is_dir() {
+            return Err(Error::Config(format!(
+                "Profile directory not found: {}",
+                dir.display()
+            )));
+        }
+        let path = dir.join(PROFILE_FILE);
+        let mut profile: Profile = if path.exists() {
+            toml::from_str(&read_to_string(&path)?).map_err(|e| {
+                Error::Config(format!(
+                    "Failed to parse {}: {e}",
+                    path.display()
+                ))
+            })?
+        } else {
+            Profile::default()
+        };
+        profile.dir = dir.to_path_buf();
+        Ok(profile)
+    }
+
+    /// Override `args` with profile settings, options passed on the
+    /// command line always win.
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) {
+        let from_cli = |id: &str| {
+            matches.value_source(id) == Some(ValueSource::CommandLine)
+        };
+        if !from_cli("email") {
+            args.email = self.email.or(args.email.take());
+        }
+        if let (Some(domain), false) = (self.domain, from_cli("domain")) {
+            args.domain = domain;
+        }
+        if let (Some(base), false) = (self.base, from_cli("base")) {
+            args.base = base;
+        }
+        if let (Some(root), false) = (self.root, from_cli("root")) {
+            args.root = root;
+        }
+        if let (Some(auth_url), false) = (self.auth_url, from_cli("auth_url")) {
+            args.auth_url = auth_url;
+        }
+        if let (Some(dir), false) = (self.output_dir, from_cli("output_dir")) {
+            args.output_dir = dir;
+        }
+        if let (Some(css), false) =
+            (self.login_email_selector, from_cli("login_email_selector"))
+        {
+            args.login_email_selector = css;
+        }
+        if let (Some(css), false) = (
+            self.login_password_selector,
+            from_cli("login_password_selector"),
+        ) {
+            args.login_password_selector = css;
+        }
+        if let (Some(css), false) = (
+            self.login_submit_selector,
+            from_cli("login_submit_selector"),
+        ) {
+            args.login_submit_selector = css;
+        }
+        if let (Some(checks), false) =
+            (self.login_success, from_cli("login_success"))
+        {
+            args.login_success = checks;
+        }
+        if !from_cli("cookie_file") {
+            args.cookie_file = self.dir.join(COOKIE_FILE);
+        }
+        let credentials = self.dir.join(CREDENTIALS_FILE);
+        if !from_cli("credentials_file") && credentials.exists() {
+            args.credentials_file = Some(credentials);
+        }
+    }
+}
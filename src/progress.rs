@@ -0,0 +1,203 @@
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use indicatif::{
+    MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle,
+};
+use tracing_subscriber::fmt::MakeWriter;
+
+use crate::ffmpeg::ProgressUpdate;
+
+/// Crawl counters on the top line and a bar per running download.
+pub struct Progress {
+    multi: MultiProgress,
+    status: OnceLock<ProgressBar>,
+    pages: AtomicU64,
+    queued: AtomicU64,
+    started: AtomicU64,
+    downloaded: AtomicU64,
+    failed: AtomicU64,
+}
+
+impl Progress {
+    /// Progress view drawn only if stdout is a terminal. Otherwise nothing
+    /// is drawn and logs are all there is.
+    pub fn terminal() -> Self {
+        if std::io::stdout().is_terminal() {
+            Progress::new(MultiProgress::with_draw_target(
+                ProgressDrawTarget::stdout(),
+            ))
+        } else {
+            Progress::hidden()
+        }
+    }
+
+    /// Progress view which is never drawn, counters are still kept.
+    pub fn hidden() -> Self {
+        Progress::new(MultiProgress::with_draw_target(
+            ProgressDrawTarget::hidden(),
+        ))
+    }
+
+    fn new(multi: MultiProgress) -> Self {
+        Progress {
+            multi,
+            status: OnceLock::new(),
+            pages: AtomicU64::new(0),
+            queued: AtomicU64::new(0),
+            started: AtomicU64::new(0),
+            downloaded: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+        }
+    }
+
+    pub fn page_visited(&self) {
+        self.pages.fetch_add(1, Ordering::Relaxed);
+        self.update_status();
+    }
+
+    pub fn video_queued(&self) {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        self.update_status();
+    }
+
+    pub fn download_started(&self) {
+        self.started.fetch_add(1, Ordering::Relaxed);
+        self.update_status();
+    }
+
+    pub fn download_finished(&self, success: bool) {
+        let counter = if success {
+            &self.downloaded
+        } else {
+            &self.failed
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.update_status();
+    }
+
+    /// Add a bar for the video being saved to `filepath`.
+    pub fn add_download(&self, filepath: &Path) -> Download {
+        let bar = self.multi.add(ProgressBar::new(0));
+        bar.set_style(
+            ProgressStyle::with_template("{prefix} {elapsed} {msg}").unwrap(),
+        );
+        let name = filepath.iter().rev().take(2).collect::<Vec<_>>();
+        let name = name
+            .iter()
+            .rev()
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        bar.set_prefix(name);
+        Download {
+            bar,
+            multi: self.multi.clone(),
+        }
+    }
+
+    /// Writer for the terminal log layer, it hides the view while a line
+    /// is printed.
+    pub fn log_writer(&self) -> LogWriter {
+        LogWriter {
+            multi: self.multi.clone(),
+        }
+    }
+
+    /// Clear the view, so the shell prompt doesn't land in the middle.
+    pub fn clear(&self) {
+        if let Some(status) = self.status.get() {
+            status.finish_and_clear();
+        }
+        let _ = self.multi.clear();
+    }
+
+    fn update_status(&self) {
+        let status = self.status.get_or_init(|| {
+            let status = self.multi.insert(0, ProgressBar::new_spinner());
+            status.enable_steady_tick(Duration::from_millis(200));
+            status
+        });
+        let queued = self.queued.load(Ordering::Relaxed);
+        let started = self.started.load(Ordering::Relaxed);
+        status.set_message(format!(
+            "pages visited: {}, videos found: {queued}, in queue: {}, \
+            downloaded: {}, failed: {}",
+            self.pages.load(Ordering::Relaxed),
+            queued.saturating_sub(started),
+            self.downloaded.load(Ordering::Relaxed),
+            self.failed.load(Ordering::Relaxed),
+        ));
+    }
+}
+
+/// Bar of a running download, removed once it is dropped.
+pub struct Download {
+    bar: ProgressBar,
+    multi: MultiProgress,
+}
+
+impl Download {
+    /// Length of the video, without it only speed and size are shown.
+    pub fn set_duration(&self, duration: Duration) {
+        self.bar.set_length(duration.as_millis() as u64);
+        self.bar.set_style(
+            ProgressStyle::with_template(
+                "{prefix} [{bar:30}] {percent:>3}% {msg} eta {eta}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+    }
+
+    pub fn update(&self, update: &ProgressUpdate) {
+        // Position is in video time, so eta follows download speed
+        self.bar.set_position(update.out_time.as_millis() as u64);
+        let speed = update
+            .speed
+            .map_or("-".to_string(), |speed| format!("{speed:.1}x"));
+        self.bar.set_message(format!(
+            "{speed} {}",
+            indicatif::HumanBytes(update.total_size)
+        ));
+    }
+}
+
+impl Drop for Download {
+    fn drop(&mut self) {
+        self.bar.finish_and_clear();
+        self.multi.remove(&self.bar);
+    }
+}
+
+/// Log writer which hides the progress view while a line is printed.
+#[derive(Clone)]
+pub struct LogWriter {
+    multi: MultiProgress,
+}
+
+impl<'a> MakeWriter<'a> for LogWriter {
+    type Writer = LogWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.multi.suspend(|| std::io::stdout().lock().write(buf))
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> std::io::Result<()> {
+        self.multi
+            .suspend(|| std::io::stdout().lock().write_all(buf))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        std::io::stdout().flush()
+    }
+}
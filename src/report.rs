@@ -0,0 +1,475 @@
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+
+use crate::error::{DownloadError, Error};
+use crate::failed::FailureKind;
+use crate::video_saver::VideoInfo;
+
+/// Report files, written into the output directory.
+pub const REPORT_JSON_FILE: &str = "report.json";
+pub const REPORT_HTML_FILE: &str = "report.html";
+
+/// What happened to a video.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    /// Queued, the run ended before its outcome was known
+    Queued,
+    Downloaded,
+    Failed,
+    Interrupted,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct VideoReport {
+    #[serde(flatten)]
+    pub info: VideoInfo,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure: Option<FailureKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_secs: Option<f64>,
+}
+
+/// Course page visited by the crawler.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageReport {
+    pub url: String,
+    /// Position of the link on each page leading to this one, see
+    /// [`crate::index`]
+    #[serde(default)]
+    pub order: Vec<usize>,
+    /// Titles of the pages leading to this one
+    pub breadcrumb: Vec<String>,
+    pub title: String,
+    pub videos: Vec<VideoReport>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Totals {
+    pub pages: usize,
+    pub videos: usize,
+    pub downloaded: usize,
+    pub failed: usize,
+    /// Interrupted or still queued when the run ended
+    pub interrupted: usize,
+    pub bytes: u64,
+    pub duration_secs: f64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RunReport {
+    #[serde(with = "time::serde::rfc3339")]
+    pub started_at: OffsetDateTime,
+    #[serde(with = "time::serde::rfc3339")]
+    pub finished_at: OffsetDateTime,
+    /// Time spent crawling, none if nothing was crawled
+    pub crawl_secs: Option<f64>,
+    pub total_secs: f64,
+    pub totals: Totals,
+    pub pages: Vec<PageReport>,
+    /// Videos not found on a page of this run, e.g. retried ones
+    pub other_videos: Vec<VideoReport>,
+}
+
+/// Collects pages and video outcomes while the run goes on.
+pub struct Report {
+    started: Instant,
+    started_at: OffsetDateTime,
+    crawl: Mutex<Option<Duration>>,
+    pages: Mutex<Vec<PageReport>>,
+    other_videos: Mutex<Vec<VideoReport>>,
+}
+
+impl Default for Report {
+    fn default() -> Self {
+        Report::new()
+    }
+}
+
+impl Report {
+    /// Start the report, its clock runs from now.
+    pub fn new() -> Self {
+        Report {
+            started: Instant::now(),
+            started_at: OffsetDateTime::now_utc(),
+            crawl: Mutex::new(None),
+            pages: Mutex::new(Vec::new()),
+            other_videos: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn page_visited(
+        &self,
+        url: &str,
+        order: &[usize],
+        breadcrumb: &[String],
+        title: &str,
+    ) {
+        self.pages.lock().unwrap().push(PageReport {
+            url: url.to_string(),
+            order: order.to_vec(),
+            breadcrumb: breadcrumb.to_vec(),
+            title: title.to_string(),
+            videos: Vec::new(),
+        });
+    }
+
+    pub fn crawl_finished(&self) {
+        *self.crawl.lock().unwrap() = Some(self.started.elapsed());
+    }
+
+    pub fn video_queued(&self, info: &VideoInfo) {
+        self.update(info, |_| {});
+    }
+
+    pub fn video_downloaded(
+        &self,
+        info: &VideoInfo,
+        file: &Path,
+        duration: Option<Duration>,
+    ) {
+        let size = std::fs::metadata(file).ok().map(|m| m.len());
+        self.update(info, |video| {
+            video.outcome = Outcome::Downloaded;
+            video.failure = None;
+            video.message = None;
+            video.file = Some(file.to_path_buf());
+            video.size = size;
+            video.duration_secs = duration.map(|d| d.as_secs_f64());
+        });
+    }
+
+    pub fn video_failed(&self, info: &VideoInfo, error: &DownloadError) {
+        self.update(info, |video| {
+            video.outcome = match error {
+                DownloadError::Interrupted => Outcome::Interrupted,
+                _ => Outcome::Failed,
+            };
+            video.failure = Some(error.kind());
+            video.message = Some(error.to_string());
+        });
+    }
+
+    /// Apply `f` to the entry of the video, added on the page it was found
+    /// on if it is new.
+    fn update(&self, info: &VideoInfo, f: impl FnOnce(&mut VideoReport)) {
+        let mut pages = self.pages.lock().unwrap();
+        let mut other_videos = self.other_videos.lock().unwrap();
+        let existing = pages
+            .iter_mut()
+            .flat_map(|page| page.videos.iter_mut())
+            .chain(other_videos.iter_mut())
+            .find(|video| video.info.same_video(info));
+        if let Some(video) = existing {
+            video.info = info.clone();
+            return f(video);
+        }
+        let mut video = VideoReport {
+            info: info.clone(),
+            outcome: Outcome::Queued,
+            failure: None,
+            message: None,
+            file: None,
+            size: None,
+            duration_secs: None,
+        };
+        f(&mut video);
+        let page = info.source.as_ref().and_then(|source| {
+            pages.iter_mut().rev().find(|page| page.url == source.page)
+        });
+        match page {
+            Some(page) => page.videos.push(video),
+            None => other_videos.push(video),
+        }
+    }
+
+    /// Snapshot of the run so far, with totals.
+    pub fn finish(&self) -> RunReport {
+        let pages = self.pages.lock().unwrap().clone();
+        let other_videos = self.other_videos.lock().unwrap().clone();
+        let mut totals = Totals {
+            pages: pages.len(),
+            ..Totals::default()
+        };
+        for video in pages
+            .iter()
+            .flat_map(|page| &page.videos)
+            .chain(&other_videos)
+        {
+            totals.videos += 1;
+            match video.outcome {
+                Outcome::Downloaded => totals.downloaded += 1,
+                Outcome::Failed => totals.failed += 1,
+                Outcome::Interrupted | Outcome::Queued => {
+                    totals.interrupted += 1
+                }
+            }
+            totals.bytes += video.size.unwrap_or(0);
+            totals.duration_secs += video.duration_secs.unwrap_or(0.0);
+        }
+        RunReport {
+            started_at: self.started_at,
+            finished_at: OffsetDateTime::now_utc(),
+            crawl_secs: self.crawl.lock().unwrap().map(|d| d.as_secs_f64()),
+            total_secs: self.started.elapsed().as_secs_f64(),
+            totals,
+            pages,
+            other_videos,
+        }
+    }
+}
+
+impl RunReport {
+    /// Write the report as json and html into `dir`.
+    pub fn write(&self, dir: &Path) -> Result<(), Error> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| {
+            Error::Internal(format!("Failed to serialize report: {e}"))
+        })?;
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(dir.join(REPORT_JSON_FILE), json)?;
+        std::fs::write(dir.join(REPORT_HTML_FILE), render_html(self, dir))?;
+        Ok(())
+    }
+}
+
+/// Section of the course tree, pages are placed by their breadcrumb.
+#[derive(Default)]
+struct Node<'a> {
+    name: String,
+    pages: Vec<&'a PageReport>,
+    children: Vec<Node<'a>>,
+}
+
+impl<'a> Node<'a> {
+    fn insert(&mut self, path: &[String], page: &'a PageReport) {
+        let Some((name, rest)) = path.split_first() else {
+            self.pages.push(page);
+            return;
+        };
+        let idx = match self.children.iter().position(|c| &c.name == name) {
+            Some(idx) => idx,
+            None => {
+                self.children.push(Node {
+                    name: name.clone(),
+                    ..Node::default()
+                });
+                self.children.len() - 1
+            }
+        };
+        self.children[idx].insert(rest, page);
+    }
+
+    fn video_count(&self) -> usize {
+        self.pages.iter().map(|p| p.videos.len()).sum::<usize>()
+            + self.children.iter().map(Node::video_count).sum::<usize>()
+    }
+}
+
+fn render_html(report: &RunReport, dir: &Path) -> String {
+    let mut root = Node::default();
+    for page in &report.pages {
+        let mut path = page.breadcrumb.clone();
+        path.push(page.title.clone());
+        root.insert(&path, page);
+    }
+    let totals = &report.totals;
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>Download report</title>\n<style>{STYLE}</style>\n</head>\n\
+        <body>\n<h1>Download report</h1>\n<p>Started {}, took {}{}</p>\n\
+        <table>\n<tr><th>Pages</th><th>Videos</th><th>Downloaded</th>\
+        <th>Failed</th><th>Interrupted</th><th>Size</th><th>Duration</th>\
+        </tr>\n<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>\
+        <td>{}</td><td>{}</td></tr>\n</table>\n",
+        report.started_at,
+        format_secs(report.total_secs),
+        report
+            .crawl_secs
+            .map(|s| format!(", crawl took {}", format_secs(s)))
+            .unwrap_or_default(),
+        totals.pages,
+        totals.videos,
+        totals.downloaded,
+        totals.failed,
+        totals.interrupted,
+        indicatif::HumanBytes(totals.bytes),
+        format_secs(totals.duration_secs),
+    );
+    for node in &root.children {
+        render_node(&mut html, node, dir);
+    }
+    if !report.other_videos.is_empty() {
+        let _ = write!(
+            html,
+            "<details open>\n<summary>Other videos ({})</summary>\n",
+            report.other_videos.len()
+        );
+        render_videos(&mut html, &report.other_videos, dir);
+        html.push_str("</details>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_node(html: &mut String, node: &Node, dir: &Path) {
+    let _ = write!(
+        html,
+        "<details open>\n<summary>{} ({} videos)</summary>\n",
+        escape(&node.name),
+        node.video_count()
+    );
+    for page in &node.pages {
+        let _ = writeln!(
+            html,
+            "<p class=\"page\"><a href=\"{0}\">{0}</a></p>",
+            escape(&page.url)
+        );
+        render_videos(html, &page.videos, dir);
+    }
+    for child in &node.children {
+        render_node(html, child, dir);
+    }
+    html.push_str("</details>\n");
+}
+
+fn render_videos(html: &mut String, videos: &[VideoReport], dir: &Path) {
+    if videos.is_empty() {
+        return;
+    }
+    html.push_str("<ul>\n");
+    for video in videos {
+        let outcome = match video.outcome {
+            Outcome::Queued => "queued",
+            Outcome::Downloaded => "downloaded",
+            Outcome::Failed => "failed",
+            Outcome::Interrupted => "interrupted",
+        };
+        let _ = write!(html, "<li class=\"{outcome}\">{outcome} ");
+        match &video.file {
+            Some(file) => {
+                // Report lies in the output dir, link files relative to it
+                let link = file.strip_prefix(dir).unwrap_or(file);
+                let _ = write!(
+                    html,
+                    "<a href=\"{}\">{}</a>",
+                    href(link),
+                    escape(&file.to_string_lossy())
+                );
+            }
+            None => html.push_str(&escape(&video.info.path)),
+        }
+        if let Some(size) = video.size {
+            let _ = write!(html, ", {}", indicatif::HumanBytes(size));
+        }
+        if let Some(secs) = video.duration_secs {
+            let _ = write!(html, ", {}", format_secs(secs));
+        }
+        if let Some(message) = &video.message {
+            let _ = write!(html, ": {}", escape(message));
+        }
+        html.push_str("</li>\n");
+    }
+    html.push_str("</ul>\n");
+}
+
+pub(crate) fn format_secs(secs: f64) -> String {
+    let secs = secs.round() as u64;
+    format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+pub(crate) fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Relative link to a local file, lesson titles are full of spaces and
+/// other characters which are not allowed in urls.
+pub(crate) fn href(path: &Path) -> String {
+    let mut href = String::new();
+    for (idx, part) in path.to_string_lossy().split('/').enumerate() {
+        if idx > 0 {
+            href.push('/');
+        }
+        for byte in part.bytes() {
+            match byte {
+                b'A'..=b'Z'
+                | b'a'..=b'z'
+                | b'0'..=b'9'
+                | b'-'
+                | b'_'
+                | b'.'
+                | b'~' => href.push(char::from(byte)),
+                _ => {
+                    let _ = write!(href, "%{byte:02X}");
+                }
+            }
+        }
+    }
+    href
+}
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; }
+details { margin-left: 1.2em; }
+summary { cursor: pointer; font-weight: bold; }
+.page { margin: 0.3em 1.2em; color: #666; }
+.downloaded { color: #2a7a2a; }
+.failed { color: #b02020; }
+.interrupted, .queued { color: #a06000; }";
+
+#[cfg(test)]
+mod tests {
+    use super::Report;
+    use crate::error::DownloadError;
+    use crate::video_saver::VideoInfo;
+
+    fn video(page: &str, frame: usize) -> VideoInfo {
+        VideoInfo::test("Course").found_on(page, frame)
+    }
+
+    #[test]
+    fn keeps_last_outcome_on_its_page() {
+        let report = Report::new();
+        report.page_visited(
+            "lesson-1",
+            &[0],
+            &["Course".to_string()],
+            "Lesson 1",
+        );
+        report.video_queued(&video("lesson-1", 0));
+        report.video_queued(&video("lesson-1", 1));
+        report.video_queued(&video("unknown", 0));
+        report.video_failed(&video("lesson-1", 0), &DownloadError::Expired);
+        report.video_downloaded(
+            &video("lesson-1", 0),
+            "missing.mp4".as_ref(),
+            None,
+        );
+        report.video_failed(&video("lesson-1", 1), &DownloadError::NoUrls);
+
+        let run = report.finish();
+        assert_eq!(run.pages[0].videos.len(), 2);
+        assert_eq!(run.other_videos.len(), 1);
+        assert_eq!(run.totals.videos, 3);
+        assert_eq!(run.totals.downloaded, 1);
+        assert_eq!(run.totals.failed, 1);
+        assert_eq!(run.totals.interrupted, 1);
+    }
+}
@@ -0,0 +1,328 @@
+use std::fs::read_to_string;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+use thirtyfour::{By, Cookie, WebDriver};
+
+use crate::cookies::{self, CookieFormat};
+use crate::credentials::CredentialSource;
+use crate::error::Error;
+use crate::site::SiteAdapter;
+
+use super::{write_file, CrawlOptions};
+
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(15);
+/// User needs time to type credentials and solve a captcha
+const INTERACTIVE_LOGIN_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// Condition which tells that login has succeeded.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum LoginCheck {
+    /// Browser has left the login page
+    UrlChange,
+    /// Element matching css selector is present
+    Element(String),
+    /// No element matches css selector, e.g. an error banner
+    NoElement(String),
+}
+
+impl std::fmt::Display for LoginCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoginCheck::UrlChange => write!(f, "url-change"),
+            LoginCheck::Element(css) => write!(f, "element:{css}"),
+            LoginCheck::NoElement(css) => write!(f, "no-element:{css}"),
+        }
+    }
+}
+
+impl From<LoginCheck> for String {
+    fn from(check: LoginCheck) -> Self {
+        check.to_string()
+    }
+}
+
+impl TryFrom<String> for LoginCheck {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl FromStr for LoginCheck {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            None if s == "url-change" => Ok(LoginCheck::UrlChange),
+            Some(("element", css)) => Ok(LoginCheck::Element(css.into())),
+            Some(("no-element", css)) => Ok(LoginCheck::NoElement(css.into())),
+            _ => Err(format!(
+                "expected `url-change`, `element:<css>` or \
+                `no-element:<css>`, got `{s}`"
+            )),
+        }
+    }
+}
+
+/// Css selectors of the login form.
+#[derive(Clone, Debug)]
+pub struct LoginForm {
+    pub email: String,
+    pub password: String,
+    pub submit: String,
+    pub success: Vec<LoginCheck>,
+}
+
+/// Everything needed to get an authenticated browser session.
+pub(crate) struct Auth {
+    site: Arc<dyn SiteAdapter>,
+    domain: String,
+    root: String,
+    auth_url: String,
+    form: LoginForm,
+    credentials: CredentialSource,
+    /// Let user log in by hand in the browser window
+    interactive: bool,
+    cookie_file: PathBuf,
+    cookie_format: Option<CookieFormat>,
+    /// Parallel sessions notice expired session at about the same time,
+    /// let them log in one by one
+    relogin: tokio::sync::Mutex<()>,
+}
+
+impl Auth {
+    pub(crate) fn new(
+        options: &CrawlOptions,
+        site: Arc<dyn SiteAdapter>,
+    ) -> Self {
+        let login = &options.login;
+        Auth {
+            site,
+            domain: options.domain.clone(),
+            root: options.root.clone(),
+            auth_url: login.auth_url.clone(),
+            form: login.form.clone(),
+            credentials: CredentialSource::new(
+                login.email.clone(),
+                login.credentials_file.clone(),
+            ),
+            interactive: login.interactive,
+            cookie_file: login.cookie_file.clone(),
+            cookie_format: login.cookie_format,
+            relogin: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Open root page, using stored cookies if there are any, or logging
+    /// in with credentials otherwise.
+    pub(crate) async fn load_root_page(
+        &self,
+        wd: &WebDriver,
+    ) -> Result<(), Error> {
+        let cookies = match cookies::read_cookies(&self.cookie_file) {
+            Ok(cookies) => drop_expired(cookies),
+            Err(e) => {
+                tracing::info!("Failed to read cookies: {e}");
+                return self.login(wd).await;
+            }
+        };
+        if cookies.is_empty() {
+            tracing::info!("All stored cookies have expired");
+            return self.login(wd).await;
+        }
+
+        self.restore_cookies(wd, cookies).await?;
+        if !self.is_logged_in(wd).await? {
+            tracing::warn!("Stored cookies are stale, logging in again");
+            self.login(wd).await?;
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn restore_cookies(
+        &self,
+        wd: &WebDriver,
+        cookies: Vec<Cookie>,
+    ) -> Result<(), Error> {
+        wd.goto(&format!("{}:{}", self.domain, self.root)).await?;
+        for cookie in cookies::for_domain(cookies, &self.domain) {
+            let name = cookie.name.clone();
+            if let Err(e) = wd.add_cookie(cookie).await {
+                tracing::warn!("Browser rejected cookie {name}: {e}");
+            }
+        }
+        wd.goto(&format!("{}:{}", self.domain, self.root)).await?;
+        Ok(())
+    }
+
+    /// Log in again after the session has expired in the middle of a crawl.
+    /// Sessions waiting for the lock take the cookies stored by the one
+    /// which has logged in first.
+    pub(crate) async fn relogin(&self, wd: &WebDriver) -> Result<(), Error> {
+        let _guard = self.relogin.lock().await;
+        if self.restore_stored_cookies(wd).await? {
+            tracing::info!("Session is restored from stored cookies");
+            return Ok(());
+        }
+        tracing::warn!("Session has expired, logging in again");
+        self.login(wd).await
+    }
+
+    /// Restore cookies from the cookie file, tells if the session is live
+    /// with them.
+    async fn restore_stored_cookies(
+        &self,
+        wd: &WebDriver,
+    ) -> Result<bool, Error> {
+        let cookies = match cookies::read_cookies(&self.cookie_file) {
+            Ok(cookies) => drop_expired(cookies),
+            Err(_) => return Ok(false),
+        };
+        if cookies.is_empty() {
+            return Ok(false);
+        }
+        self.restore_cookies(wd, cookies).await?;
+        self.is_logged_in(wd).await
+    }
+
+    /// Probe current page to see if the session is live.
+    async fn is_logged_in(&self, wd: &WebDriver) -> Result<bool, Error> {
+        Ok(!self.is_login_page(wd).await?)
+    }
+
+    /// Check if we were thrown out to the login page.
+    pub(crate) async fn is_login_page(
+        &self,
+        wd: &WebDriver,
+    ) -> Result<bool, Error> {
+        if self.is_login_url(wd.current_url().await?.as_str()) {
+            return Ok(true);
+        }
+        Ok(!wd.find_all(By::Css(&self.form.password)).await?.is_empty())
+    }
+
+    fn is_login_url(&self, url: &str) -> bool {
+        let login_url = self.auth_url.split('?').next().unwrap_or_default();
+        url.starts_with(login_url)
+    }
+
+    async fn login(&self, wd: &WebDriver) -> Result<(), Error> {
+        if self.interactive {
+            self.interactive_login(wd).await?;
+        } else {
+            self.form_login(wd).await?;
+        }
+
+        let cookies = wd.get_all_cookies().await?;
+        self.store_cookies(&cookies)?;
+        tracing::info!("Finished to store cookies");
+        Ok(())
+    }
+
+    /// Open login page and wait for user to pass it, whatever challenges
+    /// (captcha, confirmation code) it shows.
+    async fn interactive_login(&self, wd: &WebDriver) -> Result<(), Error> {
+        wd.goto(&self.auth_url).await?;
+        tracing::info!(
+            "Log in in the browser window, waiting up to {:?}",
+            INTERACTIVE_LOGIN_TIMEOUT
+        );
+        self.wait_login_success(wd, INTERACTIVE_LOGIN_TIMEOUT).await
+    }
+
+    async fn form_login(&self, wd: &WebDriver) -> Result<(), Error> {
+        // Resolving may prompt on the terminal
+        let source = self.credentials.clone();
+        let credentials = tokio::task::spawn_blocking(move || source.resolve())
+            .await
+            .map_err(|e| Error::Auth(format!("Credentials prompt: {e}")))??;
+
+        wd.goto(&self.auth_url).await?;
+        self.site.login(wd, &self.form, &credentials).await?;
+        // Password is wiped here, not at the end of the crawl
+        drop(credentials);
+        self.wait_login_success(wd, LOGIN_TIMEOUT).await
+    }
+
+    /// Wait until all success conditions hold, so we never store cookies
+    /// of a failed login.
+    async fn wait_login_success(
+        &self,
+        wd: &WebDriver,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let mut failed = None;
+            for check in &self.form.success {
+                if !self.check(wd, check).await? {
+                    failed = Some(check);
+                    break;
+                }
+            }
+            let Some(check) = failed else {
+                return Ok(());
+            };
+            if tokio::time::Instant::now() > deadline {
+                return Err(Error::Auth(format!(
+                    "Bad credentials: login did not succeed, \
+                    condition {check:?} does not hold"
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(250)).await;
+        }
+    }
+
+    async fn check(
+        &self,
+        wd: &WebDriver,
+        check: &LoginCheck,
+    ) -> Result<bool, Error> {
+        Ok(match check {
+            LoginCheck::UrlChange => {
+                !self.is_login_url(wd.current_url().await?.as_str())
+            }
+            LoginCheck::Element(css) => {
+                !wd.find_all(By::Css(css)).await?.is_empty()
+            }
+            LoginCheck::NoElement(css) => {
+                wd.find_all(By::Css(css)).await?.is_empty()
+            }
+        })
+    }
+
+    /// Write cookies in the requested format, or in the format of the
+    /// existing cookie file, so imported `cookies.txt` stays `cookies.txt`.
+    fn store_cookies(&self, cookies: &[Cookie]) -> Result<(), Error> {
+        let format = self.cookie_format.unwrap_or_else(|| {
+            read_to_string(&self.cookie_file)
+                .map(|contents| CookieFormat::detect(&contents))
+                .unwrap_or(CookieFormat::Json)
+        });
+        let contents = cookies::format_cookies(cookies, format)?;
+        write_file(&self.cookie_file, &contents)
+    }
+}
+
+/// Drop cookies which are already expired. Session cookies, which have
+/// no expiry, are kept as we can't tell if they are still valid.
+fn drop_expired(cookies: Vec<Cookie>) -> Vec<Cookie> {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    cookies
+        .into_iter()
+        .filter(|cookie| match cookie.expiry {
+            Some(expiry) if expiry <= now => {
+                tracing::info!("Cookie has expired: {}", cookie.name);
+                false
+            }
+            _ => true,
+        })
+        .collect()
+}
@@ -0,0 +1,79 @@
+use thirtyfour::{
+    Capabilities, CapabilitiesHelper, ChromiumLikeCapabilities,
+    DesiredCapabilities, PageLoadStrategy, Proxy,
+};
+
+use crate::error::Error;
+
+/// Browser to drive, must match the webdriver at `--geckodriver-address`.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Browser {
+    /// Firefox, driven by geckodriver
+    Firefox,
+    /// Chrome or Chromium, driven by chromedriver
+    Chrome,
+}
+
+/// Build session capabilities for the `browser`. If `proxy_port` is set,
+/// all traffic goes through the local proxy and its certificates are trusted.
+pub(crate) fn capabilities(
+    browser: Browser,
+    headless: bool,
+    proxy_port: Option<u16>,
+) -> Result<Capabilities, Error> {
+    match browser {
+        Browser::Firefox => {
+            let mut caps = DesiredCapabilities::firefox();
+            if headless {
+                caps.set_headless()?;
+            }
+            setup_common(&mut caps, proxy_port)?;
+            Ok(caps.into())
+        }
+        Browser::Chrome => {
+            let mut caps = DesiredCapabilities::chrome();
+            if headless {
+                caps.set_headless()?;
+                caps.set_disable_gpu()?;
+                caps.set_no_sandbox()?;
+                caps.set_disable_dev_shm_usage()?;
+            }
+            if proxy_port.is_some() {
+                caps.set_ignore_certificate_errors()?;
+            }
+            setup_common(&mut caps, proxy_port)?;
+            Ok(caps.into())
+        }
+    }
+}
+
+fn setup_common(
+    caps: &mut impl CapabilitiesHelper,
+    proxy_port: Option<u16>,
+) -> Result<(), Error> {
+    caps.set_page_load_strategy(PageLoadStrategy::Eager)?;
+    if let Some(port) = proxy_port {
+        caps.set_proxy(Proxy::Manual {
+            http_proxy: Some(format!("127.0.0.1:{}", port)),
+            ssl_proxy: Some(format!("127.0.0.1:{}", port)),
+            socks_proxy: None,
+            socks_version: None,
+            socks_username: None,
+            socks_password: None,
+            no_proxy: None,
+            ftp_proxy: None,
+        })?;
+        caps.accept_insecure_certs(true)?;
+    }
+    Ok(())
+}
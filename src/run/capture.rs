@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use thirtyfour::WebDriver;
+use tokio::sync::mpsc::Sender;
+
+use crate::browser_capture;
+use crate::error::Error;
+use crate::proxy::Signal;
+use crate::site::SiteAdapter;
+
+/// Where player requests are taken from.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum CaptureMode {
+    /// Intercept requests with the MITM proxy
+    Proxy,
+    /// Read requests from the browser's performance timeline, no proxy needed
+    Browser,
+}
+
+/// Handle to the running capture, used by `store_video`.
+#[derive(Clone)]
+pub(crate) enum Capture {
+    Proxy(Sender<Signal>),
+    Browser(Arc<dyn SiteAdapter>),
+}
+
+impl Capture {
+    pub(crate) async fn start(&self, wd: &WebDriver) -> Result<(), Error> {
+        match self {
+            Capture::Proxy(tx) => tx
+                .send(Signal::StartListening)
+                .await
+                .map_err(|_| interceptor_stopped())?,
+            Capture::Browser(_) => browser_capture::start_listening(wd).await?,
+        }
+        Ok(())
+    }
+
+    pub(crate) async fn stop(
+        &self,
+        wd: &WebDriver,
+    ) -> Result<Vec<String>, Error> {
+        match self {
+            Capture::Proxy(tx) => {
+                let (one_tx, one_rx) = tokio::sync::oneshot::channel();
+                tx.send(Signal::StopListening(one_tx))
+                    .await
+                    .map_err(|_| interceptor_stopped())?;
+                one_rx.await.map_err(|_| interceptor_stopped())
+            }
+            Capture::Browser(site) => {
+                browser_capture::stop_listening(wd, site.as_ref()).await
+            }
+        }
+    }
+}
+
+fn interceptor_stopped() -> Error {
+    Error::Capture("Interceptor task has stopped".to_string())
+}
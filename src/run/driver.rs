@@ -0,0 +1,122 @@
+use std::net::{SocketAddr, TcpListener};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::Child;
+
+use crate::error::Error;
+use crate::shutdown::{background_command, Shutdown, TrackedChild};
+
+use super::browser::Browser;
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Webdriver binary (geckodriver or chromedriver) spawned by us.
+pub(crate) struct DriverProcess {
+    child: Child,
+    addr: SocketAddr,
+    tracked: TrackedChild,
+}
+
+impl DriverProcess {
+    /// Spawn driver for `browser` on a free local port and wait until it
+    /// accepts connections. If `binary` is not set, driver is taken from PATH.
+    /// Driver and its browser are left to us to stop, unless `shutdown` is
+    /// forced.
+    pub(crate) async fn spawn(
+        browser: Browser,
+        binary: Option<&Path>,
+        shutdown: &Shutdown,
+    ) -> Result<Self, Error> {
+        let binary = match binary {
+            Some(path) => path.as_os_str().to_owned(),
+            None => match browser {
+                Browser::Firefox => "geckodriver".into(),
+                Browser::Chrome => "chromedriver".into(),
+            },
+        };
+        let addr = free_local_addr()?;
+
+        let mut child = background_command(&binary)
+            .arg(format!("--port={}", addr.port()))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                Error::Browser(format!(
+                    "Failed to spawn webdriver {}: {e}",
+                    binary.to_string_lossy()
+                ))
+            })?;
+        if let Some(stdout) = child.stdout.take() {
+            forward_logs(stdout);
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_logs(stderr);
+        }
+
+        let tracked = shutdown.track(&child);
+        let mut driver = DriverProcess {
+            child,
+            addr,
+            tracked,
+        };
+        driver.wait_ready().await?;
+        tracing::info!("Started webdriver at {}", driver.address());
+        Ok(driver)
+    }
+
+    /// Address to pass into `WebDriver::new`.
+    pub(crate) fn address(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Kill driver process along with its browser and wait for it to exit.
+    pub(crate) async fn shutdown(mut self) {
+        self.tracked.kill();
+        if let Err(e) = self.child.kill().await {
+            tracing::error!("Failed to kill webdriver process: {e}");
+        }
+    }
+
+    async fn wait_ready(&mut self) -> Result<(), Error> {
+        let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            if let Some(status) = self.child.try_wait()? {
+                return Err(Error::Browser(format!(
+                    "Webdriver exited on startup: {status}"
+                )));
+            }
+            if TcpStream::connect(self.addr).await.is_ok() {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() > deadline {
+                return Err(Error::Browser(format!(
+                    "Webdriver is not ready after {:?}",
+                    STARTUP_TIMEOUT
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
+
+/// Ask OS for a free port. There is a small window between releasing it
+/// and the driver binding it, which is fine for a local tool.
+fn free_local_addr() -> Result<SocketAddr, Error> {
+    Ok(TcpListener::bind(("127.0.0.1", 0))?.local_addr()?)
+}
+
+fn forward_logs(output: impl AsyncRead + Unpin + Send + 'static) {
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(output).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            tracing::info!("webdriver: {line}");
+        }
+    });
+}
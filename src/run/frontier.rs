@@ -0,0 +1,118 @@
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+
+use tokio::sync::Notify;
+
+use super::href::Href;
+
+/// Page to visit along with titles of pages which led to it.
+#[derive(Debug, Clone)]
+pub(crate) struct Job {
+    pub(crate) href: Href,
+    pub(crate) filepath: Vec<String>,
+    /// Position of the link on each page leading to this one, pages sorted
+    /// by it are in the order a single session would visit them
+    pub(crate) order: Vec<usize>,
+}
+
+/// Crawl frontier and visited set shared between browser sessions.
+/// Pages are taken depth-first, so a single session walks the course
+/// in the same order as the page links.
+pub(crate) struct Frontier {
+    state: Mutex<State>,
+    notify: Notify,
+}
+
+struct State {
+    stack: VecDeque<Job>,
+    visited: HashSet<Href>,
+    in_progress: usize,
+}
+
+impl Frontier {
+    pub(crate) fn new(visited: HashSet<Href>) -> Self {
+        Frontier {
+            state: Mutex::new(State {
+                stack: VecDeque::new(),
+                visited,
+                in_progress: 0,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    /// Take next unvisited page. Returns `None` when there is nothing
+    /// left to visit and no session can discover new pages anymore.
+    pub(crate) async fn next(&self) -> Option<Job> {
+        loop {
+            let notified = self.notify.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                while let Some(job) = state.stack.pop_front() {
+                    if state.visited.insert(job.href.clone()) {
+                        state.in_progress += 1;
+                        return Some(job);
+                    }
+                }
+                if state.in_progress == 0 {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    /// Put pages found on the page taken by `next` and mark it as done.
+    pub(crate) fn done(&self, jobs: Vec<Job>) {
+        self.push(jobs);
+        let mut state = self.state.lock().unwrap();
+        state.in_progress -= 1;
+        drop(state);
+        self.notify.notify_waiters();
+    }
+
+    /// Put pages on top of the stack, keeping their order.
+    pub(crate) fn push(&self, jobs: Vec<Job>) {
+        let mut state = self.state.lock().unwrap();
+        for job in jobs.into_iter().rev() {
+            if !state.visited.contains(&job.href) {
+                state.stack.push_front(job);
+            }
+        }
+        drop(state);
+        self.notify.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{Frontier, Job};
+    use crate::run::href::Href;
+
+    fn job(href: &str) -> Job {
+        Job {
+            href: Href(href.to_string()),
+            filepath: Vec::new(),
+            order: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn walks_depth_first_and_stops() {
+        let frontier = Frontier::new(HashSet::new());
+        frontier.push(vec![job("/a"), job("/b")]);
+
+        let a = frontier.next().await.unwrap();
+        assert_eq!(a.href.as_ref(), "/a");
+        frontier.done(vec![job("/a/1"), job("/b")]);
+
+        let mut order = Vec::new();
+        while let Some(job) = frontier.next().await {
+            order.push(job.href.0);
+            frontier.done(Vec::new());
+        }
+        assert_eq!(order, ["/a/1", "/b"]);
+    }
+}
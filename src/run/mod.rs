@@ -1,369 +1,656 @@
 use std::collections::HashSet;
 use std::fs::{read_to_string, File};
 use std::io::Write;
-use std::process::exit;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
 
-use futures::future::{BoxFuture, FutureExt};
 use thirtyfour::extensions::query::ElementWaitable;
-use thirtyfour::{
-    By, CapabilitiesHelper, Cookie, DesiredCapabilities, WebDriver,
-};
-use tokio::sync::mpsc::Sender;
+use thirtyfour::{WebDriver, WebElement};
+use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::task::JoinHandle;
-
-use crate::proxy::{self, Signal};
-use crate::video_saver::{VideoInfo, VideoSaver};
-use crate::{print_err, Args};
-
+use tracing::field::Empty;
+use tracing::Instrument;
+
+use crate::error::{DownloadError, Error};
+use crate::failed::{FailedVideos, FailureKind};
+use crate::index::write_indexes;
+use crate::journal::{Journal, QueueSender};
+use crate::print_err;
+use crate::progress::Progress;
+use crate::report::{Report, REPORT_HTML_FILE};
+use crate::shutdown::Shutdown;
+use crate::site::SiteAdapter;
+use crate::video_saver::{VideoInfo, VideoSaver, VideoSource};
+
+use self::auth::Auth;
+use self::capture::Capture;
+use self::frontier::{Frontier, Job};
 use self::href::Href;
+use self::session::Session;
 
+pub mod auth;
+pub mod browser;
+pub mod capture;
+mod driver;
+mod frontier;
 mod href;
+mod options;
+mod refresh;
+mod session;
+
+pub use self::options::{BrowserOptions, CrawlOptions, LoginOptions};
+
+/// What a [`Crawler`] run does after resuming unfinished downloads.
+enum Task<'a> {
+    Crawl,
+    Download(Vec<VideoInfo>),
+    Retry(&'a [FailureKind]),
+}
 
-#[tokio::main]
-pub async fn run(args: Args) -> Result<(), anyhow::Error> {
-    // Prepare communication
-    let interceptor_tx = run_proxy().await.unwrap();
-    let (saver_tx, saver_rx) = tokio::sync::mpsc::channel(10000);
-    let video_saver = VideoSaver::new(saver_rx);
-    let video_saver_handle = video_saver.run_video_saver();
-
-    if let Some(path) = args.path_to_videos_info_file {
-        if !path.exists() {
-            tracing::error!("Not found file: {}", path.to_string_lossy());
-            exit(1);
+/// Crawls a course and downloads the videos it finds. Pages and video
+/// outcomes go into the report and the progress view, which the caller
+/// may pass in to share them.
+pub struct Crawler {
+    options: CrawlOptions,
+    site: Arc<dyn SiteAdapter>,
+    shutdown: Shutdown,
+    progress: Arc<Progress>,
+    report: Arc<Report>,
+}
+
+impl Crawler {
+    /// Once `shutdown` is triggered crawling stops, and videos which are
+    /// not downloaded yet are kept in the failed videos file.
+    pub fn new(options: CrawlOptions, shutdown: Shutdown) -> Self {
+        Crawler {
+            site: options.site.adapter(),
+            options,
+            shutdown,
+            progress: Arc::new(Progress::hidden()),
+            report: Arc::new(Report::new()),
         }
-        let contents = match read_to_string(&path) {
-            Ok(s) => s,
-            Err(e) => {
-                tracing::error!(
-                    "Failed to read file: {}, error: {e}",
-                    path.to_string_lossy()
-                );
-                exit(2);
+    }
+
+    /// Show the crawl and downloads in `progress`, nothing is drawn by
+    /// default.
+    pub fn with_progress(mut self, progress: Arc<Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Record the run in `report` instead of a report of its own.
+    pub fn with_report(mut self, report: Arc<Report>) -> Self {
+        self.report = report;
+        self
+    }
+
+    /// Crawl the course and download every found video.
+    pub async fn crawl(&self) -> Result<(), Error> {
+        self.run(Task::Crawl).await
+    }
+
+    /// Download already captured videos, e.g. read by [`read_videos_info`].
+    pub async fn download(&self, info: Vec<VideoInfo>) -> Result<(), Error> {
+        self.run(Task::Download(info)).await
+    }
+
+    /// Download videos from the failed videos file again, only those
+    /// failed for one of `kinds`, or all of them if it is empty.
+    pub async fn retry(&self, kinds: &[FailureKind]) -> Result<(), Error> {
+        self.run(Task::Retry(kinds)).await
+    }
+
+    /// Run `task`, then write the report of the run into the output
+    /// directory and merge its lessons into the course indexes.
+    async fn run(&self, task: Task<'_>) -> Result<(), Error> {
+        let output_dir = &self.options.output_dir;
+        let result = self.run_task(task).await;
+        let finished = self.report.finish();
+        match finished.write(output_dir) {
+            Ok(()) => tracing::info!(
+                "Report is written to {}",
+                output_dir.join(REPORT_HTML_FILE).display()
+            ),
+            Err(e) => tracing::error!("Failed to write report: {e}"),
+        }
+        print_err!(
+            write_indexes(&finished, output_dir, self.options.playlists),
+            ()
+        );
+        result
+    }
+
+    async fn run_task(&self, task: Task<'_>) -> Result<(), Error> {
+        let journal_path = &self.options.journal_file;
+        if let Some(dir) = journal_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let pending = Journal::pending(journal_path)?;
+        let journal = Journal::open(journal_path)?;
+        if !pending.is_empty() {
+            self.resume(pending, &journal).await?;
+        }
+
+        match task {
+            Task::Crawl => self.crawl_course(&journal).await,
+            Task::Download(info) => {
+                let mut failed = self.failed_videos()?;
+                self.download_refreshing(info, &mut failed, &journal).await
             }
-        };
-        let info: Vec<VideoInfo> = match serde_json::from_str(&contents) {
-            Ok(i) => i,
-            Err(e) => {
-                tracing::error!("Failed to deserialize videos info: {e}",);
-                exit(3);
+            Task::Retry(kinds) => {
+                let mut failed = self.failed_videos()?;
+                let info = failed.select(kinds);
+                tracing::info!("Retrying {} failed videos", info.len());
+                self.download_refreshing(info, &mut failed, &journal).await
             }
-        };
-        for i in info {
-            saver_tx.send(i).await?;
         }
+    }
+
+    async fn crawl_course(&self, journal: &Journal) -> Result<(), Error> {
+        let mut failed = self.failed_videos()?;
+
+        // Prepare communication
+        let (saver_tx, saver_rx) = tokio::sync::mpsc::channel(10000);
+        let video_saver = self.video_saver(saver_rx, journal);
+        let saver_tx = self.queue_sender(saver_tx, journal);
+        let video_saver_handle = video_saver.run_video_saver();
+
+        let crawled = self.crawl_in_sessions(&saver_tx).await;
+
+        // Videos which failed in earlier runs and are downloaded now are
+        // dropped from the failed videos file
+        let attempted = saver_tx.queued();
         // Drop tx so video saver can stop
         drop(saver_tx);
-        wait_video_saver(video_saver_handle).await;
-        exit(0);
+        let saved = wait_video_saver(video_saver_handle).await;
+
+        // Videos queued before the crawl has failed are recorded too
+        let recorded =
+            self.record_failures(saved?, &attempted, &mut failed, journal);
+        crawled.and(recorded)
     }
 
-    // Setup webdriver
-    let mut caps = DesiredCapabilities::firefox();
-    caps.set_page_load_strategy(thirtyfour::PageLoadStrategy::Eager)
-        .unwrap();
-    caps.set_proxy(thirtyfour::Proxy::Manual {
-        http_proxy: Some(format!("127.0.0.1:{}", args.proxy_port)),
-        ssl_proxy: Some(format!("127.0.0.1:{}", args.proxy_port)),
-        socks_proxy: None,
-        socks_version: None,
-        socks_username: None,
-        socks_password: None,
-        no_proxy: None,
-        ftp_proxy: None,
-    })
-    .unwrap();
-    caps.accept_insecure_certs(true).unwrap();
-    let wd = match WebDriver::new(args.geckodriver_address, caps).await {
-        Ok(wd) => wd,
-        Err(e) => {
-            tracing::error!(
-                "Seems that geckodriver is not started or wrong port is set up: {e}"
-            );
-            exit(4);
+    /// Start webdriver sessions and crawl the course with them. Sessions
+    /// are closed however the crawl ends.
+    async fn crawl_in_sessions(
+        &self,
+        saver_tx: &QueueSender,
+    ) -> Result<(), Error> {
+        let mut sessions = Vec::with_capacity(self.options.sessions.into());
+        for idx in 0..self.options.sessions {
+            match Session::start(
+                &self.options.browser,
+                &self.site,
+                idx,
+                &self.shutdown,
+            )
+            .await
+            {
+                Ok(session) => sessions.push(session),
+                Err(e) => {
+                    for session in sessions {
+                        session.close().await;
+                    }
+                    return Err(e);
+                }
+            }
         }
-    };
 
-    // Prepare paths
-    let base = &args.base;
-    let root = &args.root;
-    let domain = &args.domain;
-
-    load_root_page(
-        &wd,
-        domain,
-        root,
-        &args.password,
-        &args.email,
-        &args.auth_url,
-    )
-    .await?;
-
-    let pagename = wd.title().await.unwrap_or("NotNamedPage".to_string());
-    let filepath = vec![pagename];
-
-    let dom = wd.source().await.unwrap();
-
-    let mut checked = HashSet::new();
-    checked.insert(Href(String::from(base)));
-    checked.insert(Href(String::from(root)));
-
-    let mut hrefs = Href::from_document(&dom, &base);
-    hrefs.swap(0, 1);
-
-    print_err!(
-        process_selectors(
-            &wd,
-            &domain,
-            &base,
-            hrefs,
-            &mut checked,
-            filepath,
-            interceptor_tx,
-            saver_tx,
-        )
-        .await,
-        ()
-    );
+        let result = self.crawl_sessions(&sessions, saver_tx).await;
 
-    wait_video_saver(video_saver_handle).await;
+        // Close webdriver sessions
+        for session in sessions {
+            session.close().await;
+        }
+        result
+    }
 
-    // Close webdriver session
-    wd.quit().await.unwrap();
+    async fn crawl_sessions(
+        &self,
+        sessions: &[Session],
+        saver_tx: &QueueSender,
+    ) -> Result<(), Error> {
+        let options = &self.options;
+        let shutdown = &self.shutdown;
+        let site = &self.site;
+
+        // Prepare paths
+        let base = &options.base;
+        let root = &options.root;
+        let domain = &options.domain;
+
+        let auth = Auth::new(options, site.clone());
+        let wd = &sessions[0].wd;
+        tokio::select! {
+            result = auth.load_root_page(wd) => result,
+            () = shutdown.wait() => Err(Error::Interrupted),
+        }?;
+
+        // Share authentication with other sessions
+        let cookies = wd.get_all_cookies().await?;
+        for session in &sessions[1..] {
+            auth.restore_cookies(&session.wd, cookies.clone()).await?;
+        }
 
-    Ok(())
-}
+        let pagename = wd.title().await.unwrap_or("NotNamedPage".to_string());
+        let filepath = vec![pagename];
 
-fn process_selectors<'a>(
-    wd: &'a WebDriver,
-    domain: &'a str,
-    base: &'a str,
-    hrefs: Vec<Href>,
-    checked: &'a mut HashSet<Href>,
-    filepath: Vec<String>,
-    tx: Sender<Signal>,
-    saver_tx: Sender<VideoInfo>,
-) -> BoxFuture<'a, Result<(), anyhow::Error>> {
-    async move {
-        for href in hrefs.iter() {
-            if checked.contains(&href) {
-                continue;
-            }
-            checked.insert(href.clone());
+        let dom = wd.source().await?;
 
-            if let Err(e) =
-                wd.goto(&format!("{}:{}", domain, href.as_ref())).await
-            {
-                tracing::error!(
-                    "Failed to navigate to: {:?}, error: {e}",
-                    href
-                );
-                continue;
-            } else {
-                let dom = wd.source().await.unwrap();
-                if does_page_contains_videos(&dom) {
-                    tracing::info!(
-                        "Found page contains videos: {}",
-                        merge_path(&filepath)
-                    );
-                    print_err!(
-                        store_video(
-                            wd,
-                            tx.clone(),
-                            &filepath,
-                            saver_tx.clone()
-                        )
-                        .await,
-                        ()
-                    );
-                }
-                let mut filepath = filepath.clone();
-                filepath.push(
-                    wd.title().await.unwrap_or("NotNamedPage".to_string()),
-                );
+        let mut checked = HashSet::new();
+        checked.insert(Href(String::from(base)));
+        checked.insert(Href(String::from(root)));
 
-                // Recursion
-                print_err!(
-                    process_selectors(
-                        wd,
-                        domain,
-                        base,
-                        Href::from_document(&dom, base),
-                        checked,
-                        filepath,
-                        tx.clone(),
-                        saver_tx.clone(),
-                    )
-                    .await,
-                    ()
-                );
-            }
+        let mut hrefs: Vec<Href> =
+            site.links(&dom, base).into_iter().map(Href).collect();
+        if hrefs.len() > 1 {
+            hrefs.swap(0, 1);
         }
+
+        let ctx = Crawl {
+            site: site.clone(),
+            domain,
+            base,
+            auth: &auth,
+            frontier: Frontier::new(checked),
+            saver_tx: saver_tx.clone(),
+            shutdown: shutdown.clone(),
+            progress: &self.progress,
+            report: &self.report,
+        };
+        ctx.frontier.push(
+            hrefs
+                .into_iter()
+                .enumerate()
+                .map(|(idx, href)| Job {
+                    href,
+                    filepath: filepath.clone(),
+                    order: vec![idx],
+                })
+                .collect(),
+        );
+
+        futures::future::join_all(
+            sessions.iter().map(|session| crawl(session, &ctx)),
+        )
+        .await;
+        self.report.crawl_finished();
         Ok(())
     }
-    .boxed()
-}
 
-async fn store_video(
-    wd: &WebDriver,
-    tx: Sender<Signal>,
-    filepath: &Vec<String>,
-    saver_tx: Sender<VideoInfo>,
-) -> Result<(), anyhow::Error> {
-    for element in wd.find_all(By::Css("iframe.vhi-iframe")).await? {
-        // Go into iframe
-        element.wait_until().displayed().await?;
-        element.enter_frame().await?;
-
-        //Try to find one of two tags
-        let sleep = tokio::time::sleep(Duration::from_secs(1));
-        tokio::pin!(sleep);
-        tx.send(Signal::StartListening).await?;
-        loop {
-            tokio::select! {
-                biased;
-                Ok(button) = wd.find(By::Css("button.cnf-button--confirm")) => {
-                    print_err!(button.click().await, ());
-                    break;
-                }
-                () = &mut sleep, if !sleep.is_elapsed() => {
-                    tracing::info!("Search for confirm button is timed out");
-                }
-                Ok(player_root) = wd.find(By::Id("player-root")), if sleep.is_elapsed() => {
-                    print_err!(player_root.click().await, ());
-                    break;
-                }
-                else => {
-                    tracing::warn!("Can't find player-root or confirm-button, trying again");
-                    tokio::time::sleep(Duration::from_millis(100)).await;
-                }
-            }
+    /// Continue downloads left unfinished by a crashed run, or keep them
+    /// in the failed videos file if the user doesn't want to.
+    async fn resume(
+        &self,
+        pending: Vec<VideoInfo>,
+        journal: &Journal,
+    ) -> Result<(), Error> {
+        let mut failed = self.failed_videos()?;
+        if !self.options.resume.confirm(pending.len())? {
+            let interrupted: Vec<_> = pending
+                .into_iter()
+                .map(|video| (video, DownloadError::Interrupted))
+                .collect();
+            failed.record(&[], &interrupted);
+            failed.save()?;
+            return journal.clear();
         }
 
-        // Wait for urls collection in proxy
-        tokio::time::sleep(Duration::from_secs(2)).await;
+        tracing::info!("Continuing {} unfinished downloads", pending.len());
+        match self
+            .download_refreshing(pending, &mut failed, journal)
+            .await
+        {
+            // Failures are recorded, go on with the run
+            Ok(()) | Err(Error::Download { .. }) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
 
-        // Fetch urls from proxy
-        let (one_tx, one_rx) = tokio::sync::oneshot::channel();
-        tx.send(Signal::StopListening(one_tx)).await?;
-        match one_rx.await {
-            Ok(urls) => {
-                saver_tx
-                    .send(VideoInfo {
-                        path: merge_path(filepath),
-                        urls,
-                    })
-                    .await?;
+    /// Download already captured videos, recording the outcome in
+    /// `failed`. Videos whose urls have expired are captured again from
+    /// their lesson pages and downloaded once more.
+    async fn download_refreshing(
+        &self,
+        info: Vec<VideoInfo>,
+        failed: &mut FailedVideos,
+        journal: &Journal,
+    ) -> Result<(), Error> {
+        let shutdown = &self.shutdown;
+        let failures = self.save_videos(info.clone(), journal).await?;
+        let (expired, mut failures): (Vec<_>, Vec<_>) =
+            failures.into_iter().partition(|(info, e)| {
+                matches!(e, DownloadError::Expired) && info.source.is_some()
+            });
+        if !expired.is_empty() && !shutdown.is_triggered() {
+            tracing::info!(
+                "Urls of {} videos have expired, capturing them again",
+                expired.len()
+            );
+            let (fresh, stale) = refresh::refresh_urls(
+                &self.options,
+                &self.site,
+                expired,
+                shutdown,
+            )
+            .await;
+            failures.extend(stale);
+            failures.extend(self.save_videos(fresh, journal).await?);
+        } else {
+            failures.extend(expired);
+        }
+        self.record_failures(failures, &info, failed, journal)
+    }
+
+    async fn save_videos(
+        &self,
+        info: Vec<VideoInfo>,
+        journal: &Journal,
+    ) -> Result<Vec<(VideoInfo, DownloadError)>, Error> {
+        let (saver_tx, saver_rx) = tokio::sync::mpsc::channel(10000);
+        let video_saver_handle =
+            self.video_saver(saver_rx, journal).run_video_saver();
+        let saver_tx = self.queue_sender(saver_tx, journal);
+        let mut unsent = Vec::new();
+        for i in info {
+            // Saver stops receiving on shutdown
+            if let Err(e) = saver_tx.send(i).await {
+                unsent.push((e.0, DownloadError::Interrupted));
             }
-            Err(e) => tracing::error!("Failed to get urls: {e}"),
         }
-        // Go back from iframe
-        wd.enter_parent_frame().await.unwrap();
+        // Drop tx so video saver can stop
+        drop(saver_tx);
+        let mut failures = wait_video_saver(video_saver_handle).await?;
+        failures.append(&mut unsent);
+        Ok(failures)
+    }
+
+    fn failed_videos(&self) -> Result<FailedVideos, Error> {
+        FailedVideos::load(&self.options.failed_file)
+    }
+
+    fn video_saver(
+        &self,
+        rx: Receiver<VideoInfo>,
+        journal: &Journal,
+    ) -> VideoSaver {
+        VideoSaver::new(
+            rx,
+            self.options.output_dir.clone(),
+            self.site.clone(),
+            self.shutdown.clone(),
+        )
+        .with_journal(journal.clone())
+        .with_progress(self.progress.clone())
+        .with_report(self.report.clone())
+    }
+
+    fn queue_sender(
+        &self,
+        tx: Sender<VideoInfo>,
+        journal: &Journal,
+    ) -> QueueSender {
+        QueueSender::new(
+            tx,
+            journal.clone(),
+            self.progress.clone(),
+            self.report.clone(),
+        )
+    }
+
+    /// Merge videos which failed to download into the failed videos file.
+    /// Videos from `attempted` which have been downloaded this time are
+    /// dropped from it.
+    fn record_failures(
+        &self,
+        failures: Vec<(VideoInfo, DownloadError)>,
+        attempted: &[VideoInfo],
+        failed: &mut FailedVideos,
+        journal: &Journal,
+    ) -> Result<(), Error> {
+        failed.record(attempted, &failures);
+        for (info, error) in &failures {
+            self.report.video_failed(info, error);
+        }
+        match failed.save() {
+            // Outcome of every queued video is saved, journal is not needed
+            Ok(()) => print_err!(journal.clear(), ()),
+            Err(e) => tracing::error!("Failed to save failed videos: {e}"),
+        }
+        if self.shutdown.is_triggered() {
+            return Err(Error::Interrupted);
+        }
+        if failures.is_empty() {
+            tracing::info!(
+                "There are no failed videos to download, congratulations!"
+            );
+            return Ok(());
+        }
+        for (info, error) in &failures {
+            tracing::warn!(
+                "Failed to download video in {}: {error}",
+                info.path
+            );
+        }
+        Err(Error::Download {
+            count: failures.len(),
+            file: failed.path().to_path_buf(),
+        })
     }
-    Ok(())
 }
 
-async fn run_proxy() -> Result<Sender<Signal>, anyhow::Error> {
-    let (tx, rx) = tokio::sync::mpsc::channel(10000);
-    proxy::run_interceptor(rx).await;
-    Ok(tx)
+/// Read videos info saved by a previous run, e.g. `failed_videos_data.json`.
+pub fn read_videos_info(path: &Path) -> Result<Vec<VideoInfo>, Error> {
+    let contents = read_to_string(path).map_err(|e| {
+        Error::Config(format!(
+            "Failed to read file: {}, error: {e}",
+            path.display()
+        ))
+    })?;
+    serde_json::from_str(&contents).map_err(|e| {
+        Error::Config(format!("Failed to deserialize videos info: {e}"))
+    })
 }
 
-async fn wait_video_saver(handle: JoinHandle<Vec<(VideoInfo, anyhow::Error)>>) {
-    match handle.await {
-        Ok(failed) => {
-            if failed.is_empty() {
-                tracing::info!(
-                    "There are no failed videos to download, congratulations!"
-                );
-            } else {
-                tracing::warn!("Got failed videos to download: {:?}", failed);
-                match serde_json::to_string_pretty(
-                    &failed
-                        .into_iter()
-                        .map(|(info, _)| info)
-                        .collect::<Vec<VideoInfo>>(),
-                ) {
-                    Ok(s) => print_err!(
-                        write_file("failed_videos_data.json", &s),
-                        ()
-                    ),
-                    Err(e) => tracing::error!(
-                        "Failed to serialize failed videos data: {e}"
-                    ),
-                }
-            }
+/// State shared by all sessions during the crawl.
+struct Crawl<'a> {
+    site: Arc<dyn SiteAdapter>,
+    domain: &'a str,
+    base: &'a str,
+    auth: &'a Auth,
+    frontier: Frontier,
+    saver_tx: QueueSender,
+    shutdown: Shutdown,
+    progress: &'a Progress,
+    report: &'a Report,
+}
+
+/// Visit pages from the shared frontier until the whole course is walked.
+/// Stops on shutdown, leaving the page being processed.
+async fn crawl(session: &Session, ctx: &Crawl<'_>) {
+    loop {
+        let job = tokio::select! {
+            biased;
+            () = ctx.shutdown.wait() => break,
+            job = ctx.frontier.next() => job,
+        };
+        let Some(job) = job else {
+            break;
+        };
+        tokio::select! {
+            biased;
+            () = ctx.shutdown.wait() => break,
+            jobs = process_page(session, ctx, job) => ctx.frontier.done(jobs),
         }
+    }
+}
+
+/// Store videos from the page and return pages it links to.
+#[tracing::instrument(
+    name = "page",
+    skip_all,
+    fields(
+        url = %format_args!("{}:{}", ctx.domain, job.href.as_ref()),
+        path = %merge_path(&job.filepath),
+    )
+)]
+async fn process_page(
+    session: &Session,
+    ctx: &Crawl<'_>,
+    job: Job,
+) -> Vec<Job> {
+    let wd = &session.wd;
+    let Job {
+        href,
+        filepath,
+        order,
+    } = job;
+    let url = format!("{}:{}", ctx.domain, href.as_ref());
+    let dom = match open_page(wd, ctx.auth, &url).await {
+        Ok(dom) => dom,
         Err(e) => {
-            tracing::error!("Failed to join video saver: {}", e);
+            tracing::error!("Failed to navigate to: {:?}, error: {e}", href);
+            return Vec::new();
         }
+    };
+    let title = wd.title().await.unwrap_or("NotNamedPage".to_string());
+    ctx.progress.page_visited();
+    ctx.report.page_visited(&url, &order, &filepath, &title);
+    if ctx.site.is_video_page(&dom) {
+        tracing::info!("Found page contains videos: {}", merge_path(&filepath));
+        print_err!(
+            store_video(
+                wd,
+                ctx.site.as_ref(),
+                &session.capture,
+                &url,
+                &filepath,
+                &ctx.saver_tx
+            )
+            .await,
+            ()
+        );
     }
+    let mut filepath = filepath;
+    filepath.push(title);
+
+    ctx.site
+        .links(&dom, ctx.base)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, path)| Job {
+            href: Href(path),
+            filepath: filepath.clone(),
+            order: order.iter().copied().chain([idx]).collect(),
+        })
+        .collect()
 }
 
-// ───── Helpers ──────────────────────────────────────────────────────────── //
+/// Navigate to `url` and return page source. If the session has expired
+/// and we got the login page instead, log in again and retry once.
+async fn open_page(
+    wd: &WebDriver,
+    auth: &Auth,
+    url: &str,
+) -> Result<String, Error> {
+    wd.goto(url).await?;
+    if !auth.is_login_page(wd).await? {
+        return Ok(wd.source().await?);
+    }
+
+    auth.relogin(wd).await?;
+    wd.goto(url).await?;
+    if auth.is_login_page(wd).await? {
+        return Err(Error::Auth("Still on login page after login".to_string()));
+    }
+    Ok(wd.source().await?)
+}
 
-async fn load_root_page(
+async fn store_video(
     wd: &WebDriver,
-    domain: &str,
-    root: &str,
-    password: &str,
-    email: &str,
-    auth_url: &str,
-) -> Result<(), anyhow::Error> {
-    match read_cookies() {
-        Ok(cookies) => {
-            wd.goto(&format!("{}:{}", domain, root)).await?;
-            for cookie in cookies {
-                wd.add_cookie(cookie).await?;
+    site: &dyn SiteAdapter,
+    capture: &Capture,
+    page: &str,
+    filepath: &[String],
+    saver_tx: &QueueSender,
+) -> Result<(), Error> {
+    let frames = wd.find_all(site.player_frames()).await?;
+    for (frame, element) in frames.into_iter().enumerate() {
+        let span = tracing::info_span!("video", frame, id = Empty);
+        match capture_frame(wd, site, capture, element)
+            .instrument(span.clone())
+            .await
+        {
+            Ok(urls) => {
+                span.record(
+                    "id",
+                    urls.iter().find_map(|url| site.video_id(url)),
+                );
+                span.in_scope(|| tracing::debug!("Captured urls: {urls:?}"));
+                saver_tx
+                    .send(VideoInfo {
+                        path: merge_path(filepath),
+                        urls,
+                        source: Some(VideoSource {
+                            page: page.to_string(),
+                            frame,
+                        }),
+                    })
+                    .await
+                    .map_err(|_| saver_stopped())?;
+            }
+            Err(e) => {
+                span.in_scope(|| tracing::error!("Failed to get urls: {e}"))
             }
-            wd.goto(&format!("{}:{}", domain, root)).await?;
-        }
-        Err(e) => {
-            tracing::info!("Failed to read cookies: {e}");
-            wd.goto(auth_url).await?;
-            wd.find(By::Css("input.form-field-email"))
-                .await?
-                .send_keys(email)
-                .await?;
-            wd.find(By::Css("input.form-field-password"))
-                .await?
-                .send_keys(password)
-                .await?;
-            wd.find(By::Css("button.btn-success-sech"))
-                .await?
-                .click()
-                .await?;
-
-            let cookies = wd.get_all_cookies().await?;
-            store_cookies(cookies)?;
-            tracing::info!("Finished to store cookies");
         }
     }
     Ok(())
 }
 
-fn store_cookies(cookies: Vec<Cookie>) -> anyhow::Result<()> {
-    let cookies = serde_json::to_string(&cookies)?;
-    write_file("cookie.txt", &cookies)
+/// Play the video in the player frame and return urls it requested.
+async fn capture_frame(
+    wd: &WebDriver,
+    site: &dyn SiteAdapter,
+    capture: &Capture,
+    element: WebElement,
+) -> Result<Vec<String>, Error> {
+    // Go into iframe
+    element.wait_until().displayed().await?;
+    element.enter_frame().await?;
+
+    capture.start(wd).await?;
+    site.start_player(wd).await?;
+
+    // Wait for urls collection
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // Fetch collected urls
+    let urls = capture.stop(wd).await;
+    // Go back from iframe
+    wd.enter_parent_frame().await?;
+    urls
 }
 
-fn write_file(filepath: &str, content: &str) -> anyhow::Result<()> {
-    let mut output = File::create(filepath).unwrap();
-    write!(output, "{}", content)?;
-    Ok(())
+/// Wait for the saver to finish and return videos which failed to
+/// download.
+async fn wait_video_saver(
+    handle: JoinHandle<Vec<(VideoInfo, DownloadError)>>,
+) -> Result<Vec<(VideoInfo, DownloadError)>, Error> {
+    handle.await.map_err(|e| {
+        Error::Internal(format!("Failed to join video saver: {e}"))
+    })
 }
 
-fn read_cookies() -> anyhow::Result<Vec<Cookie>> {
-    let cookie_str = read_to_string("cookie.txt")?;
-    Ok(serde_json::from_str(&cookie_str)?)
-}
+// ───── Helpers ──────────────────────────────────────────────────────────── //
 
-fn does_page_contains_videos(html: &str) -> bool {
-    html.contains("vhplayeriframe")
+fn write_file(filepath: impl AsRef<Path>, content: &str) -> Result<(), Error> {
+    let mut output = File::create(filepath)?;
+    write!(output, "{}", content)?;
+    Ok(())
 }
 
-fn merge_path(filepath: &Vec<String>) -> String {
+fn merge_path(filepath: &[String]) -> String {
     filepath
         .iter()
         .fold(Default::default(), |mut acc: String, elem| {
@@ -373,3 +660,7 @@ fn merge_path(filepath: &Vec<String>) -> String {
         })[1..]
         .to_string()
 }
+
+fn saver_stopped() -> Error {
+    Error::Internal("Video saver has stopped".to_string())
+}
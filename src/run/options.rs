@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use crate::cookies::CookieFormat;
+use crate::journal::Resume;
+use crate::site::Site;
+
+use super::auth::LoginForm;
+use super::browser::Browser;
+use super::capture::CaptureMode;
+
+/// What to crawl and where to save it, see [`Crawler`](super::Crawler).
+#[derive(Clone, Debug)]
+pub struct CrawlOptions {
+    /// Platform the course is hosted on
+    pub site: Site,
+    /// Scheme and host, e.g. `https://example.com`
+    pub domain: String,
+    /// Path prefix of course pages, links outside of it are not followed
+    pub base: String,
+    /// Path of the page listing the courses, the crawl starts there
+    pub root: String,
+    /// Parallel browser sessions, at least one
+    pub sessions: u16,
+    pub output_dir: PathBuf,
+    /// Write an `.m3u` playlist into each section directory
+    pub playlists: bool,
+    /// What to do with downloads left unfinished by a crashed run
+    pub resume: Resume,
+    /// Journal of the download queue, runs with the same journal continue
+    /// each other's unfinished downloads
+    pub journal_file: PathBuf,
+    /// Videos which failed to download, merged over runs and read by
+    /// [`Crawler::retry`](super::Crawler::retry)
+    pub failed_file: PathBuf,
+    pub browser: BrowserOptions,
+    pub login: LoginOptions,
+}
+
+/// How to drive the browser and capture player requests.
+#[derive(Clone, Debug)]
+pub struct BrowserOptions {
+    pub browser: Browser,
+    pub headless: bool,
+    /// Address of the running webdriver, unused if `spawn_driver` is set
+    pub webdriver_address: String,
+    pub spawn_driver: bool,
+    /// Webdriver binary to spawn, found in PATH if not set
+    pub driver_path: Option<PathBuf>,
+    pub capture: CaptureMode,
+    /// Proxy port of the first session, the next ones count up from it
+    pub proxy_port: u16,
+}
+
+/// How to log in and where to keep the session cookies.
+#[derive(Clone, Debug)]
+pub struct LoginOptions {
+    pub auth_url: String,
+    /// Taken from env var, credentials file or prompt if not set
+    pub email: Option<String>,
+    pub credentials_file: Option<PathBuf>,
+    /// Wait for the user to log in in the browser window
+    pub interactive: bool,
+    pub form: LoginForm,
+    pub cookie_file: PathBuf,
+    pub cookie_format: Option<CookieFormat>,
+}
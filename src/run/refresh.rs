@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use crate::error::{DownloadError, Error};
+use crate::shutdown::Shutdown;
+use crate::site::SiteAdapter;
+use crate::video_saver::VideoInfo;
+
+use super::auth::Auth;
+use super::session::Session;
+use super::{capture_frame, open_page, CrawlOptions};
+
+/// Capture fresh urls of the videos by visiting their lesson pages again.
+/// Videos which could not be refreshed are returned back with their
+/// original error, or as interrupted once `shutdown` is triggered.
+pub(crate) async fn refresh_urls(
+    options: &CrawlOptions,
+    site: &Arc<dyn SiteAdapter>,
+    expired: Vec<(VideoInfo, DownloadError)>,
+    shutdown: &Shutdown,
+) -> (Vec<VideoInfo>, Vec<(VideoInfo, DownloadError)>) {
+    let session =
+        match Session::start(&options.browser, site, 0, shutdown).await {
+            Ok(session) => session,
+            Err(e) => {
+                tracing::error!("Failed to start browser to refresh urls: {e}");
+                return (Vec::new(), expired);
+            }
+        };
+    let auth = Auth::new(options, site.clone());
+    if let Err(e) = auth.load_root_page(&session.wd).await {
+        tracing::error!("Failed to log in to refresh urls: {e}");
+        session.close().await;
+        return (Vec::new(), expired);
+    }
+
+    let mut fresh = Vec::new();
+    let mut stale = Vec::new();
+    for (info, error) in expired {
+        if shutdown.is_triggered() {
+            stale.push((info, DownloadError::Interrupted));
+            continue;
+        }
+        match recapture(&session, site.as_ref(), &auth, &info).await {
+            Ok(urls) => fresh.push(VideoInfo { urls, ..info }),
+            Err(e) => {
+                tracing::error!("Failed to refresh urls of {}: {e}", info.path);
+                stale.push((info, error));
+            }
+        }
+    }
+    session.close().await;
+    (fresh, stale)
+}
+
+async fn recapture(
+    session: &Session,
+    site: &dyn SiteAdapter,
+    auth: &Auth,
+    info: &VideoInfo,
+) -> Result<Vec<String>, Error> {
+    let Some(source) = &info.source else {
+        return Err(Error::Capture("Lesson page is unknown".to_string()));
+    };
+    let wd = &session.wd;
+    open_page(wd, auth, &source.page).await?;
+    let element = wd
+        .find_all(site.player_frames())
+        .await?
+        .into_iter()
+        .nth(source.frame)
+        .ok_or_else(|| {
+            Error::Capture(format!(
+                "Player {} not found on {}",
+                source.frame, source.page
+            ))
+        })?;
+    capture_frame(wd, site, &session.capture, element).await
+}
@@ -0,0 +1,133 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use thirtyfour::WebDriver;
+use tokio::sync::mpsc::Sender;
+
+use crate::error::Error;
+use crate::print_err;
+use crate::proxy::{self, ProxyHandle, Signal};
+use crate::shutdown::Shutdown;
+use crate::site::SiteAdapter;
+
+use super::browser;
+use super::capture::{Capture, CaptureMode};
+use super::driver::DriverProcess;
+use super::BrowserOptions;
+
+/// Browser session along with its own capture channel, so that urls
+/// requested by different sessions are never mixed.
+pub(crate) struct Session {
+    pub(crate) wd: WebDriver,
+    pub(crate) capture: Capture,
+    driver: Option<DriverProcess>,
+    proxy: Option<ProxyHandle>,
+}
+
+impl Session {
+    /// Start session number `idx`. In proxy mode every session gets its
+    /// own proxy listening on `proxy_port + idx`.
+    pub(crate) async fn start(
+        options: &BrowserOptions,
+        site: &Arc<dyn SiteAdapter>,
+        idx: u16,
+        shutdown: &Shutdown,
+    ) -> Result<Self, Error> {
+        let (capture, proxy) = match options.capture {
+            CaptureMode::Proxy => {
+                let port = proxy_port(options.proxy_port, idx)?;
+                let (tx, proxy) = run_proxy(port, site.clone())?;
+                (Capture::Proxy(tx), Some((port, proxy)))
+            }
+            CaptureMode::Browser => (Capture::Browser(site.clone()), None),
+        };
+        let (proxy_port, proxy) = proxy.unzip();
+        match open_browser(options, proxy_port, shutdown).await {
+            Ok((wd, driver)) => Ok(Session {
+                wd,
+                capture,
+                driver,
+                proxy,
+            }),
+            Err(e) => {
+                if let Some(proxy) = proxy {
+                    proxy.stop().await;
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Close webdriver session, stop the driver if we own it and the proxy.
+    pub(crate) async fn close(self) {
+        print_err!(self.wd.quit().await, ());
+        if let Some(driver) = self.driver {
+            driver.shutdown().await;
+        }
+        // Port is reused by the next session with the same number
+        if let Some(proxy) = self.proxy {
+            proxy.stop().await;
+        }
+    }
+}
+
+async fn open_browser(
+    options: &BrowserOptions,
+    proxy_port: Option<u16>,
+    shutdown: &Shutdown,
+) -> Result<(WebDriver, Option<DriverProcess>), Error> {
+    let caps =
+        browser::capabilities(options.browser, options.headless, proxy_port)?;
+    // Geckodriver can't run more than one session, so each session
+    // gets its own driver process
+    let driver = if options.spawn_driver {
+        Some(
+            DriverProcess::spawn(
+                options.browser,
+                options.driver_path.as_deref(),
+                shutdown,
+            )
+            .await?,
+        )
+    } else {
+        None
+    };
+    let address = match &driver {
+        Some(driver) => driver.address(),
+        None => options.webdriver_address.clone(),
+    };
+    match WebDriver::new(address, caps).await {
+        Ok(wd) => Ok((wd, driver)),
+        Err(e) => {
+            if let Some(driver) = driver {
+                driver.shutdown().await;
+            }
+            Err(Error::Browser(format!(
+                "Seems that webdriver is not started or wrong port is set up: {e}"
+            )))
+        }
+    }
+}
+
+/// Port of the proxy of session `idx`.
+fn proxy_port(first: u16, idx: u16) -> Result<u16, Error> {
+    first.checked_add(idx).ok_or_else(|| {
+        Error::Config(format!(
+            "Proxy port of session {idx} is above 65535, lower the proxy \
+            port or the number of sessions"
+        ))
+    })
+}
+
+fn run_proxy(
+    port: u16,
+    site: Arc<dyn SiteAdapter>,
+) -> Result<(Sender<Signal>, ProxyHandle), Error> {
+    let (tx, rx) = tokio::sync::mpsc::channel(10000);
+    let proxy = proxy::run_interceptor(
+        SocketAddr::from(([127, 0, 0, 1], port)),
+        site,
+        rx,
+    )?;
+    Ok((tx, proxy))
+}
@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::sync::{Arc, Mutex};
+
+use tokio::process::{Child, Command};
+use tokio::sync::watch;
+
+/// Exit code used when the run is interrupted, as shells do for SIGINT.
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+/// Shutdown request shared by the crawler and the video saver. Once it is
+/// triggered crawling stops, running downloads are cancelled and pending
+/// ones are recorded as interrupted.
+#[derive(Clone)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+    forced: Arc<watch::Sender<bool>>,
+    /// Process groups of running children, killed once shutdown is forced
+    children: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            tx: Arc::new(watch::Sender::new(false)),
+            forced: Arc::new(watch::Sender::new(false)),
+            children: Arc::default(),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Shutdown::default()
+    }
+
+    /// Trigger shutdown on Ctrl-C. The second Ctrl-C forces it, see
+    /// [`Shutdown::force`].
+    pub fn listen_ctrl_c(&self) {
+        let shutdown = self.clone();
+        tokio::spawn(async move {
+            if tokio::signal::ctrl_c().await.is_err() {
+                return;
+            }
+            tracing::warn!(
+                "Stopping, saving pending videos. Press Ctrl-C again to exit now"
+            );
+            shutdown.trigger();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                shutdown.force();
+            }
+        });
+    }
+
+    /// Stop without waiting for anything: child processes are killed and
+    /// [`Shutdown::forced`] returns, it is up to the caller to exit.
+    pub fn force(&self) {
+        self.trigger();
+        self.forced.send_replace(true);
+        for pid in self.children.lock().unwrap().drain() {
+            kill_group(pid);
+        }
+    }
+
+    /// Wait until shutdown is forced.
+    pub async fn forced(&self) {
+        let mut rx = self.forced.subscribe();
+        let _ = rx.wait_for(|forced| *forced).await;
+    }
+
+    /// Kill the process group of `child` if shutdown is forced while the
+    /// returned guard is alive. `child` has to be spawned from
+    /// [`background_command`].
+    pub fn track(&self, child: &Child) -> TrackedChild {
+        let pid = child.id();
+        if let Some(pid) = pid {
+            let mut children = self.children.lock().unwrap();
+            if *self.forced.borrow() {
+                kill_group(pid);
+            } else {
+                children.insert(pid);
+            }
+        }
+        TrackedChild {
+            pid,
+            children: self.children.clone(),
+        }
+    }
+
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Wait until shutdown is triggered.
+    pub async fn wait(&self) {
+        let mut rx = self.tx.subscribe();
+        // Sender lives in `self`, so the channel can't be closed
+        let _ = rx.wait_for(|triggered| *triggered).await;
+    }
+}
+
+/// Child process tracked by [`Shutdown::track`].
+pub struct TrackedChild {
+    pid: Option<u32>,
+    children: Arc<Mutex<HashSet<u32>>>,
+}
+
+impl TrackedChild {
+    /// Kill the whole process group of the child, the child is still to
+    /// be waited for.
+    pub fn kill(&self) {
+        if let Some(pid) = self.pid {
+            kill_group(pid);
+        }
+    }
+}
+
+impl Drop for TrackedChild {
+    fn drop(&mut self) {
+        if let Some(pid) = self.pid {
+            self.children.lock().unwrap().remove(&pid);
+        }
+    }
+}
+
+/// Command in its own process group, Ctrl-C on the terminal doesn't reach
+/// it. It is stopped by us on shutdown instead, so ffmpeg doesn't fail a
+/// download which counts as interrupted, and the webdriver lives until its
+/// session is quit.
+pub fn background_command(program: impl AsRef<OsStr>) -> Command {
+    #[allow(unused_mut)]
+    let mut command = std::process::Command::new(program);
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    Command::from(command)
+}
+
+#[cfg(unix)]
+fn kill_group(pid: u32) {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return;
+    };
+    // SAFETY: kill has no memory effects, the group was created by
+    // `background_command` with the child as its leader
+    if unsafe { libc::kill(-pid, libc::SIGKILL) } != 0 {
+        let e = std::io::Error::last_os_error();
+        // Group is gone already
+        if e.raw_os_error() != Some(libc::ESRCH) {
+            tracing::error!("Failed to kill process group {pid}: {e}");
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_group(_pid: u32) {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use super::{background_command, Shutdown};
+
+    #[tokio::test]
+    async fn force_kills_tracked_process_group() {
+        let shutdown = Shutdown::new();
+        // Shell waits for its own child, both have to be killed
+        let mut child = background_command("sh")
+            .args(["-c", "sleep 30; true"])
+            .spawn()
+            .unwrap();
+        let _tracked = shutdown.track(&child);
+        shutdown.force();
+        let status = tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            child.wait(),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+        assert!(shutdown.is_triggered());
+    }
+}
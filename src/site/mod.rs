@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use thirtyfour::{By, WebDriver};
+
+use crate::credentials::Credentials;
+use crate::error::{DownloadError, Error};
+use crate::run::auth::LoginForm;
+
+pub use self::getcourse::GetCourse;
+
+mod getcourse;
+
+/// Platform specific knowledge, so the crawler core stays the same
+/// for every supported platform.
+pub trait SiteAdapter: Send + Sync {
+    /// Fill and submit login form on the already opened login page.
+    fn login<'a>(
+        &'a self,
+        wd: &'a WebDriver,
+        form: &'a LoginForm,
+        credentials: &'a Credentials,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Paths to crawl which are linked from the page, `base` is the
+    /// prefix of the course section paths.
+    fn links(&self, html: &str, base: &str) -> Vec<String>;
+
+    /// Check if the page has videos to download.
+    fn is_video_page(&self, html: &str) -> bool;
+
+    /// Frames containing players on the video page.
+    fn player_frames(&self) -> By;
+
+    /// Make the player inside the current frame request its stream.
+    fn start_player<'a>(
+        &'a self,
+        wd: &'a WebDriver,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Check if the request goes to the video stream and should be collected.
+    fn is_stream_url(&self, url: &str) -> bool;
+
+    /// Pick the url to download among the ones captured from one player,
+    /// e.g. the one of the highest quality.
+    fn select_stream(&self, urls: &[String]) -> Result<String, DownloadError>;
+
+    /// Id of the video in its stream url, the same for all qualities. Used
+    /// to follow the video in logs and to name its file.
+    fn video_id<'a>(&self, url: &'a str) -> Option<&'a str>;
+
+    /// Quality of the stream, e.g. its height, which tells it apart from
+    /// other streams of the same video.
+    fn quality<'a>(&self, url: &'a str) -> Option<&'a str>;
+}
+
+/// Supported platforms.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    clap::ValueEnum,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Site {
+    /// GetCourse based schools
+    Getcourse,
+}
+
+impl Site {
+    pub fn adapter(self) -> Arc<dyn SiteAdapter> {
+        match self {
+            Site::Getcourse => Arc::new(GetCourse),
+        }
+    }
+}